@@ -0,0 +1,259 @@
+use std::{
+    io::{self, IsTerminal},
+    path::Path,
+};
+
+use crate::{
+    editor::{NAME, VERSION},
+    view::Location,
+};
+
+#[derive(Debug, Clone)]
+pub struct FileArg {
+    pub path: String,
+    pub location: Option<Location>,
+}
+
+#[derive(Debug, Default)]
+pub struct Args {
+    pub files: Vec<FileArg>,
+    pub stdin: bool,
+    pub stdin_location: Option<Location>,
+    pub readonly: bool,
+    pub pager: bool,
+    pub tab_width: Option<usize>,
+    pub session: Option<String>,
+    pub theme: Option<String>,
+    pub help: bool,
+    pub version: bool,
+}
+
+impl Args {
+    pub fn parse<I: Iterator<Item = String>>(mut iter: I) -> Result<Self, String> {
+        let mut args = Self::default();
+        let mut pending_line = None;
+        let mut only_files = false;
+        while let Some(arg) = iter.next() {
+            if only_files {
+                args.push_file(&arg, pending_line.take());
+                continue;
+            }
+            match arg.as_str() {
+                "--" => only_files = true,
+                "-h" | "--help" => args.help = true,
+                "-V" | "--version" => args.version = true,
+                "-R" | "--readonly" => args.readonly = true,
+                "-p" | "--pager" => args.pager = true,
+                "-" => {
+                    if args.stdin {
+                        return Err(String::from("stdin can only be read once"));
+                    }
+                    args.stdin = true;
+                    args.stdin_location = pending_line.take();
+                }
+                "--tab-width" => {
+                    let value = iter
+                        .next()
+                        .ok_or_else(|| String::from("option '--tab-width' requires a number"))?;
+                    args.tab_width = Some(Self::parse_tab_width(&value)?);
+                }
+                _ if arg.starts_with("--tab-width=") => {
+                    let value = arg.trim_start_matches("--tab-width=");
+                    args.tab_width = Some(Self::parse_tab_width(value)?);
+                }
+                "--session" => {
+                    let name = iter
+                        .next()
+                        .ok_or_else(|| String::from("option '--session' requires a name"))?;
+                    args.session = Some(name);
+                }
+                _ if arg.starts_with("--session=") => {
+                    args.session = Some(arg.trim_start_matches("--session=").to_string());
+                }
+                "--theme" => {
+                    let name = iter
+                        .next()
+                        .ok_or_else(|| String::from("option '--theme' requires a name"))?;
+                    args.theme = Some(name);
+                }
+                _ if arg.starts_with("--theme=") => {
+                    args.theme = Some(arg.trim_start_matches("--theme=").to_string());
+                }
+                _ if arg.starts_with('+') => {
+                    let line = Self::parse_number(&arg[1..])
+                        .ok_or_else(|| format!("invalid line number '{arg}'"))?;
+                    pending_line = Some(Location {
+                        line_index: line.saturating_sub(1),
+                        grapheme_index: 0,
+                    });
+                }
+                _ if arg.starts_with('-') => return Err(format!("unknown option '{arg}'")),
+                _ => args.push_file(&arg, pending_line.take()),
+            }
+        }
+        if pending_line.is_some() {
+            return Err(String::from("'+LINE' must be followed by a file"));
+        }
+        if !args.stdin
+            && args.files.is_empty()
+            && args.session.is_none()
+            && !io::stdin().is_terminal()
+        {
+            args.stdin = true;
+        }
+        if args.session.as_deref() == Some("") {
+            return Err(String::from("session name cannot be empty"));
+        }
+        Ok(args)
+    }
+
+    pub fn usage() -> String {
+        format!(
+            "Usage: {NAME} [OPTIONS] [+LINE] [FILE[:LINE[:COL]]]...
+
+Options:
+  -                   Read the text from standard input (the default
+                      when standard input is not a terminal)
+  -R, --readonly      Open the files read-only
+  -p, --pager         View the text like a pager, without editing
+      --session NAME  Restore the session NAME and save it on exit
+      --tab-width N   Display tabs N columns wide (default 4)
+      --theme NAME    Use the color theme NAME (built in: default, dark)
+  -h, --help          Print this help and exit
+  -V, --version       Print the version and exit
+"
+        )
+    }
+
+    pub fn version() -> String {
+        format!("{NAME} {VERSION}")
+    }
+
+    fn push_file(&mut self, arg: &str, location: Option<Location>) {
+        let file = if Path::new(arg).exists() {
+            None
+        } else {
+            Self::split_location(arg)
+        };
+        self.files.push(file.unwrap_or_else(|| FileArg {
+            path: arg.to_string(),
+            location,
+        }));
+    }
+
+    fn split_location(arg: &str) -> Option<FileArg> {
+        let mut parts = arg.rsplitn(3, ':');
+        let last = parts.next()?;
+        let middle = parts.next()?;
+        let (path, line, col) = match (parts.next(), Self::parse_number(middle)) {
+            (Some(path), Some(line)) => (path, line, Self::parse_number(last)?),
+            _ => (arg.rsplit_once(':')?.0, Self::parse_number(last)?, 1),
+        };
+        if path.is_empty() {
+            return None;
+        }
+        Some(FileArg {
+            path: path.to_string(),
+            location: Some(Location {
+                line_index: line.saturating_sub(1),
+                grapheme_index: col.saturating_sub(1),
+            }),
+        })
+    }
+
+    fn parse_tab_width(value: &str) -> Result<usize, String> {
+        Self::parse_number(value)
+            .filter(|width| (1..=32).contains(width))
+            .ok_or_else(|| format!("invalid tab width '{value}'"))
+    }
+
+    fn parse_number(value: &str) -> Option<usize> {
+        if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        value.parse().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        Args::parse(args.iter().map(ToString::to_string))
+    }
+
+    fn location(line_index: usize, grapheme_index: usize) -> Option<Location> {
+        Some(Location {
+            line_index,
+            grapheme_index,
+        })
+    }
+
+    #[test]
+    fn split_location_line_and_column() {
+        let file = Args::split_location("src/main.rs:12:3").unwrap();
+        assert_eq!(file.path, "src/main.rs");
+        assert_eq!(file.location, location(11, 2));
+
+        let file = Args::split_location("src/main.rs:12").unwrap();
+        assert_eq!(file.path, "src/main.rs");
+        assert_eq!(file.location, location(11, 0));
+    }
+
+    #[test]
+    fn split_location_keeps_colons_in_path() {
+        let file = Args::split_location("C:\\notes.txt:7").unwrap();
+        assert_eq!(file.path, "C:\\notes.txt");
+        assert_eq!(file.location, location(6, 0));
+
+        let file = Args::split_location("a:b:12:3").unwrap();
+        assert_eq!(file.path, "a:b");
+        assert_eq!(file.location, location(11, 2));
+
+        assert!(Args::split_location("C:\\notes.txt").is_none());
+        assert!(Args::split_location("file:12:x").is_none());
+        assert!(Args::split_location(":12").is_none());
+        assert!(Args::split_location("plain").is_none());
+    }
+
+    #[test]
+    fn plus_line_applies_to_next_file() {
+        let args = parse(&["+5", "no-such-file-a", "no-such-file-b"]).unwrap();
+        assert_eq!(args.files[0].path, "no-such-file-a");
+        assert_eq!(args.files[0].location, location(4, 0));
+        assert_eq!(args.files[1].location, None);
+
+        assert!(parse(&["+x", "file"]).is_err());
+        assert!(parse(&["file", "+5"]).is_err());
+    }
+
+    #[test]
+    fn dash_reads_stdin_once() {
+        let args = parse(&["+3", "-"]).unwrap();
+        assert!(args.stdin);
+        assert_eq!(args.stdin_location, location(2, 0));
+        assert!(parse(&["-", "-"]).is_err());
+    }
+
+    #[test]
+    fn double_dash_ends_options() {
+        let args = parse(&["--", "-R", "+1"]).unwrap();
+        assert!(!args.readonly);
+        let paths: Vec<&str> = args.files.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(paths, ["-R", "+1"]);
+    }
+
+    #[test]
+    fn options_with_values() {
+        let args = parse(&["--tab-width", "2", "--session=work", "--theme", "dark", "f"]).unwrap();
+        assert_eq!(args.tab_width, Some(2));
+        assert_eq!(args.session.as_deref(), Some("work"));
+        assert_eq!(args.theme.as_deref(), Some("dark"));
+
+        assert!(parse(&["--tab-width=0", "f"]).is_err());
+        assert!(parse(&["--tab-width=33", "f"]).is_err());
+        assert!(parse(&["--session="]).is_err());
+        assert!(parse(&["--bogus"]).is_err());
+    }
+}
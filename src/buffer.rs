@@ -2,51 +2,255 @@ use std::{
     fs::{self, File},
     io::Error,
     io::Write,
+    ops::Range,
+    path::Path,
 };
 
-use crate::{fileinfo::FileInfo, line::Line, view::Location};
+use crate::{
+    directory::Directory,
+    fileinfo::FileInfo,
+    filetype::FileType,
+    highlight::{Highlighter, Span},
+    indent::{IndentRules, IndentSettings},
+    line::{Line, DEFAULT_TAB_WIDTH},
+    syntaxtree::{NodeMotion, SyntaxTree, TextEdit},
+    view::Location,
+};
 
 #[derive(Debug)]
 pub struct Buffer {
     pub lines: Vec<Line>,
     pub file_info: FileInfo,
     pub is_modify: bool,
+    pub is_readonly: bool,
+    pub directory: Option<Directory>,
+    pub indent: IndentSettings,
+    tab_width: usize,
+    highlighter: Highlighter,
+    syntax_tree: Option<SyntaxTree>,
 }
 
 impl Default for Buffer {
     fn default() -> Self {
         Buffer {
             lines: Vec::new(),
-            file_info: FileInfo { path: None },
+            file_info: FileInfo::default(),
             is_modify: false,
+            is_readonly: false,
+            directory: None,
+            indent: IndentSettings::default(),
+            tab_width: DEFAULT_TAB_WIDTH,
+            highlighter: Highlighter::default(),
+            syntax_tree: None,
         }
     }
 }
 
 impl Buffer {
     pub fn read_file(filepath: &str) -> Result<Self, std::io::Error> {
+        if Path::new(filepath).is_dir() {
+            return Self::read_dir(Path::new(filepath));
+        }
         let contents = fs::read_to_string(filepath)?;
+        let mut buffer = Self::from_text(&contents);
+        buffer.file_info = FileInfo::from(filepath);
+        buffer.detect_file_type();
+        buffer.is_readonly = !Self::is_writable(Path::new(filepath));
+        Ok(buffer)
+    }
+
+    pub fn from_text(contents: &str) -> Self {
         let mut lines = Vec::new();
         for str in contents.lines() {
             lines.push(Line::from(str));
         }
-        Ok(Self {
+        let mut buffer = Self {
             lines,
-            file_info: FileInfo::from(filepath),
+            ..Self::default()
+        };
+        buffer.detect_file_type();
+        buffer
+    }
+
+    pub fn to_text(&self) -> String {
+        self.lines.iter().map(|line| format!("{line}\n")).collect()
+    }
+
+    pub fn read_dir(path: &Path) -> Result<Self, std::io::Error> {
+        let directory = Directory::read(path)?;
+        Ok(Self {
+            lines: directory.lines(),
+            file_info: FileInfo {
+                path: Some(directory.path.clone()),
+                file_type: FileType::default(),
+            },
             is_modify: false,
+            is_readonly: false,
+            directory: Some(directory),
+            indent: IndentSettings::default(),
+            tab_width: DEFAULT_TAB_WIDTH,
+            highlighter: Highlighter::default(),
+            syntax_tree: None,
         })
     }
 
+    pub fn detect_file_type(&mut self) {
+        self.set_file_type(FileType::detect(self.file_info.get_path(), &self.lines));
+    }
+
+    pub fn set_file_type(&mut self, file_type: FileType) {
+        self.file_info.file_type = file_type;
+        self.highlighter = Highlighter::for_file_type(file_type);
+        self.syntax_tree = SyntaxTree::for_file_type(file_type);
+        // 文件本身的缩进风格优先于语言默认值
+        self.indent = IndentSettings::detect(&self.lines)
+            .unwrap_or_else(|| IndentSettings::for_file_type(file_type));
+    }
+
+    fn is_writable(path: &Path) -> bool {
+        fs::OpenOptions::new().append(true).open(path).is_ok()
+    }
+
+    pub const fn tab_width(&self) -> usize {
+        self.tab_width
+    }
+
+    pub fn set_tab_width(&mut self, tab_width: usize) {
+        self.tab_width = tab_width.max(1);
+        for line in &mut self.lines {
+            line.set_tab_width(self.tab_width);
+        }
+    }
+
+    pub fn push_line(&mut self, text: &str) {
+        let end = Location {
+            line_index: self.height(),
+            grapheme_index: 0,
+        };
+        self.record_edit(end, end, &format!("{text}\n"));
+        self.insert_line(self.height(), text);
+    }
+
+    fn insert_line(&mut self, index: usize, text: &str) {
+        let mut line = Line::from(text);
+        line.set_tab_width(self.tab_width);
+        self.lines.insert(index, line);
+        self.highlighter.invalidate(index);
+    }
+
+    pub fn pop_line(&mut self) {
+        let start = Location {
+            line_index: self.height().saturating_sub(1),
+            grapheme_index: 0,
+        };
+        let end = Location {
+            line_index: self.height(),
+            grapheme_index: 0,
+        };
+        self.record_edit(start, end, "");
+        self.lines.pop();
+        self.highlighter.invalidate(self.height());
+    }
+
+    pub fn clear_lines(&mut self) {
+        self.lines.clear();
+        self.highlighter.invalidate(0);
+        if let Some(tree) = &mut self.syntax_tree {
+            tree.reset();
+        }
+    }
+
+    fn mark_modified(&mut self, line_index: usize) {
+        self.is_modify = true;
+        self.highlighter.invalidate(line_index);
+    }
+
+    fn byte_position(&self, at: Location) -> (usize, (usize, usize)) {
+        let line_start: usize = self
+            .lines
+            .iter()
+            .take(at.line_index)
+            .map(|line| line.grapheme_to_byte_index(line.len()).saturating_add(1))
+            .sum();
+        let column = self
+            .lines
+            .get(at.line_index)
+            .map_or(0, |line| line.grapheme_to_byte_index(at.grapheme_index));
+        (line_start.saturating_add(column), (at.line_index, column))
+    }
+
+    // 必须在修改文本之前调用, 这样才能算出旧文本里的字节位置
+    fn record_edit(&mut self, start: Location, old_end: Location, inserted: &str) {
+        if self.syntax_tree.is_none() {
+            return;
+        }
+        let (start_byte, start) = self.byte_position(start);
+        let (old_end_byte, old_end) = self.byte_position(old_end);
+        let new_end = match inserted.rfind('\n') {
+            Some(newline) => (
+                start.0.saturating_add(inserted.matches('\n').count()),
+                inserted.len().saturating_sub(newline).saturating_sub(1),
+            ),
+            None => (start.0, start.1.saturating_add(inserted.len())),
+        };
+        let edit = TextEdit {
+            start_byte,
+            old_end_byte,
+            new_end_byte: start_byte.saturating_add(inserted.len()),
+            start,
+            old_end,
+            new_end,
+            inserted: inserted.to_string(),
+        };
+        if let Some(tree) = &mut self.syntax_tree {
+            tree.edit(&edit);
+        }
+    }
+
+    pub fn highlight(&mut self, range: Range<usize>) {
+        match &mut self.syntax_tree {
+            Some(tree) => tree.highlight(&self.lines, range),
+            None => self.highlighter.update(&self.lines, range.end),
+        }
+    }
+
+    pub fn spans(&self, line_index: usize) -> &[Span] {
+        match &self.syntax_tree {
+            Some(tree) => tree.spans(line_index),
+            None => self.highlighter.spans(line_index),
+        }
+    }
+
+    pub fn node_target(&mut self, at: Location, motion: NodeMotion) -> Option<Location> {
+        let (_, position) = self.byte_position(at);
+        let (row, column) =
+            self.syntax_tree
+                .as_mut()?
+                .node_target(&self.lines, position, motion)?;
+        Some(Location {
+            line_index: row,
+            grapheme_index: self
+                .lines
+                .get(row)
+                .map_or(0, |line| line.byte_to_grapheme_index(column)),
+        })
+    }
+
+    pub fn is_directory(&self) -> bool {
+        self.directory.is_some()
+    }
+
     pub fn insert_char(&mut self, s: char, at: Location) {
         if at.line_index > self.height() {
             return;
         }
         if at.line_index == self.height() {
-            self.lines.push(Line::from(&s.to_string()));
-            self.is_modify = true;
-        } else if let Some(line) = self.lines.get_mut(at.line_index) {
-            line.intert_char(s, at.grapheme_index);
-            self.is_modify = true;
+            self.push_line(&s.to_string());
+            self.mark_modified(at.line_index);
+        } else if at.line_index < self.height() {
+            self.record_edit(at, at, &s.to_string());
+            self.lines[at.line_index].intert_char(s, at.grapheme_index);
+            self.mark_modified(at.line_index);
         }
     }
 
@@ -58,25 +262,259 @@ impl Buffer {
         if let Some(line) = self.lines.get(line_index) {
             // 从非最后一行的行末删除
             if at.grapheme_index >= line.len() && self.height() > line_index.saturating_add(1) {
+                let next = Location {
+                    line_index: line_index.saturating_add(1),
+                    grapheme_index: 0,
+                };
+                self.record_edit(at, next, "");
                 let next_line = self.lines.remove(line_index.saturating_add(1));
                 self.lines[line_index].append(&next_line);
-                self.is_modify = true;
+                self.mark_modified(at.line_index);
             } else if grapheme_index < line.len() {
+                let next = Location {
+                    grapheme_index: grapheme_index.saturating_add(1),
+                    ..at
+                };
+                self.record_edit(at, next, "");
                 self.lines[line_index].delete(grapheme_index);
-                self.is_modify = true;
+                self.mark_modified(at.line_index);
+            }
+        }
+    }
+
+    pub fn insert_text(&mut self, text: &str, at: Location) -> Location {
+        if at.line_index > self.height() || text.is_empty() {
+            return at;
+        }
+        if at.line_index == self.height() {
+            self.record_edit(at, at, &format!("{text}\n"));
+            self.insert_line(at.line_index, "");
+        } else {
+            self.record_edit(at, at, text);
+        }
+        let Some(line) = self.lines.get_mut(at.line_index) else {
+            return at;
+        };
+        let rest = line.split(at.grapheme_index);
+        let mut pieces = text.split('\n');
+        line.insert_str(pieces.next().unwrap_or_default(), at.grapheme_index);
+        let mut location = Location {
+            line_index: at.line_index,
+            grapheme_index: line.len(),
+        };
+        for piece in pieces {
+            location.line_index = location.line_index.saturating_add(1);
+            self.insert_line(location.line_index, piece);
+            location.grapheme_index = self.lines[location.line_index].len();
+        }
+        self.lines[location.line_index].append(&rest);
+        self.mark_modified(at.line_index);
+        location
+    }
+
+    pub fn delete_word_backward(&mut self, at: Location) -> (Location, String) {
+        if at.grapheme_index == 0 {
+            if at.line_index == 0 || at.line_index > self.height() {
+                return (at, String::new());
             }
+            let previous = at.line_index.saturating_sub(1);
+            let location = Location {
+                line_index: previous,
+                grapheme_index: self.lines.get(previous).map_or(0, Line::len),
+            };
+            let killed = self.join_next_line(location);
+            return (location, killed);
+        }
+        let Some(line) = self.lines.get(at.line_index) else {
+            return (at, String::new());
+        };
+        let start = Location {
+            grapheme_index: line.previous_word_start(at.grapheme_index).unwrap_or(0),
+            ..at
+        };
+        (start, self.drain(start, at))
+    }
+
+    pub fn delete_word_forward(&mut self, at: Location) -> (Location, String) {
+        let Some(line) = self.lines.get(at.line_index) else {
+            return (at, String::new());
+        };
+        if at.grapheme_index >= line.len() {
+            return (at, self.join_next_line(at));
+        }
+        let end = Location {
+            grapheme_index: line.next_word_end(at.grapheme_index).unwrap_or(line.len()),
+            ..at
+        };
+        (at, self.drain(at, end))
+    }
+
+    pub fn kill_to_end_of_line(&mut self, at: Location) -> (Location, String) {
+        let Some(line) = self.lines.get(at.line_index) else {
+            return (at, String::new());
+        };
+        if at.grapheme_index >= line.len() {
+            return (at, self.join_next_line(at));
+        }
+        let end = Location {
+            grapheme_index: line.len(),
+            ..at
+        };
+        (at, self.drain(at, end))
+    }
+
+    pub fn kill_to_start_of_line(&mut self, at: Location) -> (Location, String) {
+        let start = Location {
+            grapheme_index: 0,
+            ..at
+        };
+        (start, self.drain(start, at))
+    }
+
+    fn drain(&mut self, start: Location, end: Location) -> String {
+        if start.grapheme_index >= end.grapheme_index || start.line_index >= self.height() {
+            return String::new();
         }
+        self.record_edit(start, end, "");
+        let killed = self.lines[start.line_index].drain(start.grapheme_index..end.grapheme_index);
+        self.mark_modified(start.line_index);
+        killed
     }
 
-    pub fn insert_new_line(&mut self, at: Location) {
+    pub fn delete_line(&mut self, at: Location) -> (Location, String) {
+        let location = Location {
+            grapheme_index: 0,
+            ..at
+        };
+        if at.line_index >= self.height() {
+            return (location, String::new());
+        }
+        let next = Location {
+            line_index: at.line_index.saturating_add(1),
+            grapheme_index: 0,
+        };
+        self.record_edit(location, next, "");
+        let mut killed = self.lines.remove(at.line_index).to_string();
+        killed.push('\n');
+        self.mark_modified(at.line_index);
+        (location, killed)
+    }
+
+    pub fn text_range(&self, start: Location, end: Location) -> String {
+        let mut text = String::new();
+        for line_index in start.line_index..=end.line_index {
+            let Some(line) = self.lines.get(line_index) else {
+                break;
+            };
+            let from = if line_index == start.line_index {
+                start.grapheme_index
+            } else {
+                0
+            };
+            if line_index == end.line_index {
+                text.push_str(&line.slice(from..end.grapheme_index));
+            } else {
+                text.push_str(&line.slice(from..line.len()));
+                text.push('\n');
+            }
+        }
+        text
+    }
+
+    pub fn delete_range(&mut self, start: Location, end: Location) -> String {
+        let Some(last) = self.lines.len().checked_sub(1) else {
+            return String::new();
+        };
+        let end = if end.line_index > last {
+            Location {
+                line_index: last,
+                grapheme_index: self.lines[last].len(),
+            }
+        } else {
+            Location {
+                grapheme_index: end.grapheme_index.min(self.lines[end.line_index].len()),
+                ..end
+            }
+        };
+        if start >= end || start.line_index > last {
+            return String::new();
+        }
+        let killed = self.text_range(start, end);
+        self.record_edit(start, end, "");
+        if start.line_index == end.line_index {
+            self.lines[start.line_index].drain(start.grapheme_index..end.grapheme_index);
+        } else {
+            let tail = self.lines[end.line_index].split(end.grapheme_index);
+            self.lines
+                .drain(start.line_index.saturating_add(1)..=end.line_index);
+            let line = &mut self.lines[start.line_index];
+            line.drain(start.grapheme_index..line.len());
+            line.append(&tail);
+        }
+        self.mark_modified(start.line_index);
+        killed
+    }
+
+    fn join_next_line(&mut self, at: Location) -> String {
+        if at.line_index.saturating_add(1) >= self.height() {
+            return String::new();
+        }
+        self.delete(at);
+        String::from("\n")
+    }
+
+    pub fn insert_new_line(&mut self, at: Location) -> usize {
         if at.line_index == self.height() {
-            self.lines.push(Line::default());
-            self.is_modify = true;
-        } else if let Some(line) = self.lines.get_mut(at.line_index) {
-            let new_line = line.split(at.grapheme_index);
-            self.lines.insert(at.line_index.saturating_add(1), new_line);
-            self.is_modify = true;
+            self.record_edit(at, at, "\n");
+            self.insert_line(at.line_index, "");
+            self.mark_modified(at.line_index);
+            return 0;
         }
+        let rules = self.indent_rules();
+        let unit = self.indent.unit();
+        let Some(line) = self.lines.get(at.line_index) else {
+            return 0;
+        };
+        let text = line.to_string();
+        let (before, rest) = text.split_at(line.grapheme_to_byte_index(at.grapheme_index));
+        let indent: String = before
+            .chars()
+            .take(line.indent_end().min(at.grapheme_index))
+            .collect();
+        let rest = rest.trim_start();
+        let opener = before
+            .trim_end()
+            .chars()
+            .last()
+            .filter(|c| rules.openers.contains(c));
+        let mut new_indent = indent.clone();
+        if opener.is_some() {
+            new_indent.push_str(&unit);
+        }
+        // 光标在一对括号之间时, 闭括号单独占一行
+        let inserted = if opener
+            .and_then(IndentRules::closer_for)
+            .is_some_and(|closer| rest.starts_with(closer))
+        {
+            format!("\n{new_indent}\n{indent}{rest}")
+        } else {
+            format!("\n{new_indent}{rest}")
+        };
+        let line_end = Location {
+            grapheme_index: line.len(),
+            ..at
+        };
+        self.record_edit(at, line_end, &inserted);
+        self.lines.remove(at.line_index);
+        for (offset, piece) in format!("{before}{inserted}").split('\n').enumerate() {
+            self.insert_line(at.line_index.saturating_add(offset), piece);
+        }
+        self.mark_modified(at.line_index);
+        new_indent.chars().count()
+    }
+
+    pub fn indent_rules(&self) -> IndentRules {
+        IndentRules::for_file_type(self.file_info.file_type)
     }
 
     pub fn save_to_file(&self, fileinfo: &FileInfo) -> Result<(), Error> {
@@ -93,6 +531,7 @@ impl Buffer {
         let file_info = FileInfo::from(filename);
         self.save_to_file(&file_info)?;
         self.file_info = file_info;
+        self.detect_file_type();
         self.is_modify = false;
         Ok(())
     }
@@ -115,3 +554,43 @@ impl Buffer {
         self.lines.len()
     }
 }
+
+#[cfg(all(test, feature = "tree-sitter"))]
+mod tests {
+    use super::*;
+
+    fn at(line_index: usize, grapheme_index: usize) -> Location {
+        Location {
+            line_index,
+            grapheme_index,
+        }
+    }
+
+    fn spans(buffer: &mut Buffer) -> Vec<Vec<Span>> {
+        buffer.highlight(0..buffer.height());
+        (0..buffer.height())
+            .map(|index| buffer.spans(index).to_vec())
+            .collect()
+    }
+
+    // 增量解析的结果要和重新解析整个文档一致
+    #[test]
+    fn incremental_parse_matches_full_parse() {
+        let mut buffer = Buffer::from_text("fn a() {\n    let b = 1;\n}\n");
+        buffer.set_file_type(FileType::Rust);
+        spans(&mut buffer);
+        buffer.insert_text("\"s\" // é\nstruct", at(1, 12));
+        buffer.insert_char('x', at(0, 4));
+        buffer.insert_new_line(at(0, 8));
+        buffer.delete(at(1, 0));
+        buffer.delete_range(at(2, 2), at(3, 3));
+        buffer.delete_line(at(0, 0));
+        buffer.push_line("/* tail */");
+        buffer.pop_line();
+        buffer.push_line("const C: u8 = 0;");
+        let mut fresh = Buffer::from_text(&buffer.to_text());
+        fresh.set_file_type(FileType::Rust);
+        assert_eq!(spans(&mut buffer), spans(&mut fresh));
+        assert!(spans(&mut buffer).iter().any(|line| !line.is_empty()));
+    }
+}
@@ -1,10 +1,6 @@
-use core::convert::TryFrom;
-
-use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
-
 use crate::size::Size;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Move {
     Up,
     Down,
@@ -14,111 +10,291 @@ pub enum Move {
     PageDown,
     Home,
     End,
+    WordLeft,
+    WordRight,
+    ParagraphUp,
+    ParagraphDown,
+    StartOfDocument,
+    EndOfDocument,
+    ParentNode,
+    NextSibling,
+    PreviousSibling,
 }
 
-impl TryFrom<KeyEvent> for Move {
-    type Error = String;
-    fn try_from(value: KeyEvent) -> Result<Self, Self::Error> {
-        let KeyEvent {
-            code, modifiers, ..
-        } = value;
-        if modifiers == KeyModifiers::NONE {
-            match code {
-                KeyCode::Up => Ok(Self::Up),
-                KeyCode::Down => Ok(Self::Down),
-                KeyCode::End => Ok(Self::End),
-                KeyCode::Left => Ok(Self::Left),
-                KeyCode::Home => Ok(Self::Home),
-                KeyCode::Right => Ok(Self::Right),
-                KeyCode::PageUp => Ok(Self::PageUp),
-                KeyCode::PageDown => Ok(Self::PageDown),
-                _ => Err(format!(
-                    "Unsupported key code {code:?} or modifier {modifiers:?}"
-                )),
-            }
-        } else {
-            Err(format!(
-                "Unsupported key code {code:?} or modifier {modifiers:?}"
-            ))
-        }
-    }
-}
-
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum System {
     Save,
+    OpenRecent,
+    NextBuffer,
+    PreviousBuffer,
+    SaveSession,
+    OpenSession,
+    ToggleReadonly,
+    Follow,
+    ToggleGutter,
+    ToggleWrap,
+    Yank,
+    YankPop,
+    SetMark,
+    KillRegion,
+    CopyRegion,
+    SetFileType,
+    ReloadConfig,
+    CommandPalette,
+    CommandLine,
     Resize(Size),
     Quit,
     Dismiss,
 }
 
-impl TryFrom<KeyEvent> for System {
-    type Error = String;
-    fn try_from(value: KeyEvent) -> Result<Self, Self::Error> {
-        let KeyEvent {
-            code, modifiers, ..
-        } = value;
-        match (code, modifiers) {
-            (KeyCode::Char('s'), KeyModifiers::CONTROL) => Ok(Self::Save),
-            (KeyCode::Char('q'), KeyModifiers::CONTROL) => Ok(Self::Quit),
-            (KeyCode::Esc, KeyModifiers::NONE) => Ok(Self::Dismiss),
-            _ => Err(format!(
-                "Unsupported key code {code:?} or modifier {modifiers:?}"
-            )),
-        }
-    }
-}
-
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Edit {
     Delete,
     Insert(char),
     InsertNewline,
     DeleteBackward,
+    DeleteWordBackward,
+    DeleteWordForward,
+    KillToEndOfLine,
+    KillToStartOfLine,
+    DeleteLine,
 }
 
-impl TryFrom<KeyEvent> for Edit {
-    type Error = String;
-    fn try_from(event: KeyEvent) -> Result<Self, Self::Error> {
-        match (event.code, event.modifiers) {
-            (KeyCode::Char(character), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
-                Ok(Self::Insert(character))
-            }
-            (KeyCode::Tab, KeyModifiers::NONE) => Ok(Self::Insert('\t')),
-            (KeyCode::Enter, KeyModifiers::NONE) => Ok(Self::InsertNewline),
-            (KeyCode::Backspace, KeyModifiers::NONE) => Ok(Self::DeleteBackward),
-            (KeyCode::Delete, KeyModifiers::NONE) => Ok(Self::Delete),
-            _ => Err(format!(
-                "Unsupported key code {:?} with modifiers {:?}",
-                event.code, event.modifiers
-            )),
-        }
-    }
-}
-
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Command {
     Move(Move),
     Edit(Edit),
     System(System),
 }
 
-impl TryFrom<Event> for Command {
-    type Error = String;
-    fn try_from(value: Event) -> Result<Self, Self::Error> {
-        match value {
-            Event::Resize(w_u16, h_u16) => {
-                let width = w_u16 as usize;
-                let height = h_u16 as usize;
-                let sys = System::Resize(Size { width, height });
-                Ok(Self::System(sys))
-            }
-            Event::Key(key_ev) => Edit::try_from(key_ev)
-                .map(Command::Edit)
-                .or_else(|_| Move::try_from(key_ev).map(Command::Move))
-                .or_else(|_| System::try_from(key_ev).map(Command::System))
-                .map_err(|e| format!("Event not supported: {e:?}")),
-            _ => Err(format!("Event not supported: {value:?}")),
-        }
+// 所有可绑定命令的名字和说明, 命令面板按这里的顺序列出
+pub const COMMANDS: &[(&str, &str, Command)] = &[
+    ("up", "Move cursor up", Command::Move(Move::Up)),
+    ("down", "Move cursor down", Command::Move(Move::Down)),
+    ("left", "Move cursor left", Command::Move(Move::Left)),
+    ("right", "Move cursor right", Command::Move(Move::Right)),
+    ("page_up", "Scroll up one page", Command::Move(Move::PageUp)),
+    (
+        "page_down",
+        "Scroll down one page",
+        Command::Move(Move::PageDown),
+    ),
+    ("home", "Go to start of line", Command::Move(Move::Home)),
+    ("end", "Go to end of line", Command::Move(Move::End)),
+    (
+        "word_left",
+        "Go to previous word",
+        Command::Move(Move::WordLeft),
+    ),
+    (
+        "word_right",
+        "Go to next word",
+        Command::Move(Move::WordRight),
+    ),
+    (
+        "paragraph_up",
+        "Go to previous paragraph",
+        Command::Move(Move::ParagraphUp),
+    ),
+    (
+        "paragraph_down",
+        "Go to next paragraph",
+        Command::Move(Move::ParagraphDown),
+    ),
+    (
+        "start_of_document",
+        "Go to start of file",
+        Command::Move(Move::StartOfDocument),
+    ),
+    (
+        "end_of_document",
+        "Go to end of file",
+        Command::Move(Move::EndOfDocument),
+    ),
+    (
+        "parent_node",
+        "Go to enclosing syntax node",
+        Command::Move(Move::ParentNode),
+    ),
+    (
+        "next_sibling",
+        "Go to next syntax node",
+        Command::Move(Move::NextSibling),
+    ),
+    (
+        "previous_sibling",
+        "Go to previous syntax node",
+        Command::Move(Move::PreviousSibling),
+    ),
+    (
+        "insert_tab",
+        "Insert indentation",
+        Command::Edit(Edit::Insert('\t')),
+    ),
+    (
+        "newline",
+        "Insert line break",
+        Command::Edit(Edit::InsertNewline),
+    ),
+    (
+        "delete",
+        "Delete character under cursor",
+        Command::Edit(Edit::Delete),
+    ),
+    (
+        "delete_backward",
+        "Delete character before cursor",
+        Command::Edit(Edit::DeleteBackward),
+    ),
+    (
+        "delete_word_backward",
+        "Kill word before cursor",
+        Command::Edit(Edit::DeleteWordBackward),
+    ),
+    (
+        "delete_word_forward",
+        "Kill word after cursor",
+        Command::Edit(Edit::DeleteWordForward),
+    ),
+    (
+        "kill_to_end_of_line",
+        "Kill to end of line",
+        Command::Edit(Edit::KillToEndOfLine),
+    ),
+    (
+        "kill_to_start_of_line",
+        "Kill to start of line",
+        Command::Edit(Edit::KillToStartOfLine),
+    ),
+    (
+        "delete_line",
+        "Kill current line",
+        Command::Edit(Edit::DeleteLine),
+    ),
+    ("save", "Save file", Command::System(System::Save)),
+    ("quit", "Quit editor", Command::System(System::Quit)),
+    (
+        "open_recent",
+        "Open a recent file",
+        Command::System(System::OpenRecent),
+    ),
+    (
+        "next_buffer",
+        "Switch to next buffer",
+        Command::System(System::NextBuffer),
+    ),
+    (
+        "previous_buffer",
+        "Switch to previous buffer",
+        Command::System(System::PreviousBuffer),
+    ),
+    (
+        "save_session",
+        "Save open buffers as a session",
+        Command::System(System::SaveSession),
+    ),
+    (
+        "open_session",
+        "Restore a saved session",
+        Command::System(System::OpenSession),
+    ),
+    (
+        "toggle_readonly",
+        "Toggle read-only mode",
+        Command::System(System::ToggleReadonly),
+    ),
+    (
+        "follow",
+        "Follow file as it grows",
+        Command::System(System::Follow),
+    ),
+    (
+        "toggle_gutter",
+        "Cycle line number display",
+        Command::System(System::ToggleGutter),
+    ),
+    (
+        "toggle_wrap",
+        "Toggle soft wrap",
+        Command::System(System::ToggleWrap),
+    ),
+    (
+        "yank",
+        "Paste last killed text",
+        Command::System(System::Yank),
+    ),
+    (
+        "yank_pop",
+        "Replace paste with older kill",
+        Command::System(System::YankPop),
+    ),
+    (
+        "set_mark",
+        "Set or clear the mark",
+        Command::System(System::SetMark),
+    ),
+    (
+        "kill_region",
+        "Kill marked region",
+        Command::System(System::KillRegion),
+    ),
+    (
+        "copy_region",
+        "Copy marked region",
+        Command::System(System::CopyRegion),
+    ),
+    (
+        "set_file_type",
+        "Set file type for highlighting",
+        Command::System(System::SetFileType),
+    ),
+    (
+        "reload_config",
+        "Reload configuration files",
+        Command::System(System::ReloadConfig),
+    ),
+    (
+        "command_palette",
+        "Search and run commands",
+        Command::System(System::CommandPalette),
+    ),
+    (
+        "command_line",
+        "Run an ex-style command",
+        Command::System(System::CommandLine),
+    ),
+    (
+        "dismiss",
+        "Cancel prompt or selection",
+        Command::System(System::Dismiss),
+    ),
+];
+
+impl Edit {
+    pub fn is_backward_kill(self) -> bool {
+        matches!(self, Self::DeleteWordBackward | Self::KillToStartOfLine)
+    }
+}
+
+impl Command {
+    // 连续执行时会合并进同一条 kill ring 记录
+    pub fn is_kill(self) -> bool {
+        matches!(
+            self,
+            Self::Edit(
+                Edit::DeleteWordBackward
+                    | Edit::DeleteWordForward
+                    | Edit::KillToEndOfLine
+                    | Edit::KillToStartOfLine
+                    | Edit::DeleteLine
+            ) | Self::System(System::KillRegion)
+        )
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        COMMANDS
+            .iter()
+            .find(|(command_name, _, _)| *command_name == name)
+            .map(|(_, _, command)| *command)
     }
 }
@@ -1,4 +1,11 @@
-use crate::{command::Edit, line::Line, size::Size, terminal::Terminal, uicomponent::UIComponent};
+use crate::{
+    command::{Edit, Move},
+    line::Line,
+    size::Size,
+    terminal::Terminal,
+    theme::Theme,
+    uicomponent::UIComponent,
+};
 use std::cmp::min;
 
 #[derive(Default)]
@@ -7,29 +14,130 @@ pub struct CommandBar {
     prompt: String,
     need_redraw: bool,
     value: Line,
+    theme: Theme,
+    history: Vec<String>,
+    history_index: Option<usize>,
+    // 翻阅历史前输入的内容, 只列出以它开头的记录
+    draft: String,
+    completer: Option<fn(&str) -> Vec<String>>,
+    completions: Vec<String>,
+    completion_index: usize,
 }
 
 impl CommandBar {
     pub fn handle_command_edit(&mut self, edit: Edit) {
+        if edit == Edit::Insert('\t') && self.completer.is_some() {
+            self.complete();
+            return;
+        }
+        self.completions.clear();
+        self.history_index = None;
         match edit {
             Edit::Insert(c) => self.value.append_char(c),
-            Edit::Delete | Edit::InsertNewline => {}
             Edit::DeleteBackward => self.value.delete_last(),
+            Edit::DeleteWordBackward => self.value.delete_last_word(),
+            Edit::KillToStartOfLine | Edit::DeleteLine => self.value = Line::default(),
+            Edit::Delete
+            | Edit::InsertNewline
+            | Edit::DeleteWordForward
+            | Edit::KillToEndOfLine => {}
+        }
+        self.mark_redraw(true);
+    }
+
+    pub fn handle_move(&mut self, mv: Move) {
+        match mv {
+            Move::Up => self.history_previous(),
+            Move::Down => self.history_next(),
+            _ => {}
+        }
+    }
+
+    fn history_previous(&mut self) {
+        let end = match self.history_index {
+            Some(index) => index,
+            None => {
+                self.draft = self.value();
+                self.history.len()
+            }
+        };
+        if let Some(index) = self.history[..end]
+            .iter()
+            .rposition(|entry| entry.starts_with(&self.draft))
+        {
+            self.history_index = Some(index);
+            self.value = Line::from(&self.history[index]);
+            self.completions.clear();
+            self.mark_redraw(true);
+        }
+    }
+
+    fn history_next(&mut self) {
+        let Some(current) = self.history_index else {
+            return;
+        };
+        let start = current.saturating_add(1);
+        let next = self.history[start..]
+            .iter()
+            .position(|entry| entry.starts_with(&self.draft))
+            .map(|offset| start.saturating_add(offset));
+        self.history_index = next;
+        self.value = Line::from(next.map_or(&self.draft, |index| &self.history[index]));
+        self.completions.clear();
+        self.mark_redraw(true);
+    }
+
+    // 连续按 Tab 依次切换候选项
+    fn complete(&mut self) {
+        if self.completions.is_empty() {
+            let Some(completer) = self.completer else {
+                return;
+            };
+            self.completions = completer(&self.value());
+            self.completion_index = 0;
+        } else {
+            self.completion_index =
+                self.completion_index.saturating_add(1) % self.completions.len();
         }
+        if let Some(completion) = self.completions.get(self.completion_index) {
+            self.value = Line::from(completion);
+            self.mark_redraw(true);
+        }
+    }
+
+    pub fn set_history(&mut self, history: Vec<String>) {
+        self.history = history;
+    }
+
+    pub fn set_completer(&mut self, completer: fn(&str) -> Vec<String>) {
+        self.completer = Some(completer);
     }
 
     pub fn value(&self) -> String {
         self.value.to_string()
     }
 
+    pub fn set_value(&mut self, value: &str) {
+        self.value = Line::from(value);
+        self.mark_redraw(true);
+    }
+
     pub fn set_prompt(&mut self, prompt: String) {
         self.prompt = prompt
     }
 
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
     pub fn caret_position_col(&self) -> usize {
-        let max_width = self.prompt.len().saturating_add(self.value.len());
+        let max_width = self.prompt_width().saturating_add(self.value.width());
         min(max_width, self.size.width)
     }
+
+    fn prompt_width(&self) -> usize {
+        Line::from(&self.prompt).width()
+    }
 }
 
 impl UIComponent for CommandBar {
@@ -46,15 +154,33 @@ impl UIComponent for CommandBar {
     }
 
     fn draw(&mut self, origin_y: usize) -> Result<(), std::io::Error> {
-        let val_width = self.size.width.saturating_sub(self.prompt.len());
+        let prompt_width = self.prompt_width();
+        let val_width = self.size.width.saturating_sub(prompt_width);
         let val_end = self.value.width();
         let val_start = val_end.saturating_sub(val_width);
-        let message = format!("{}{}", self.prompt, self.value.get(val_start..val_start));
-        let to_print = if message.len() <= self.size.width {
+        let message = format!("{}{}", self.prompt, self.value.get(val_start..val_end));
+        let to_print = if prompt_width <= self.size.width {
             message
         } else {
             String::new()
         };
-        Terminal::print_row(origin_y, &to_print)
+        Terminal::print_styled_row(origin_y, &to_print, self.theme.message_bar)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn caret_by_display_width() {
+        let mut bar = CommandBar::default();
+        bar.set_size(Size {
+            width: 80,
+            height: 1,
+        });
+        bar.set_prompt(String::from("替换: "));
+        bar.set_value("中文字");
+        assert_eq!(bar.caret_position_col(), 12);
     }
 }
@@ -0,0 +1,233 @@
+use crate::{
+    editor::NAME,
+    gutter::GutterMode,
+    keymap::{Keymap, Preset},
+    line::DEFAULT_TAB_WIDTH,
+    messagebar::DEFAULT_DURATION,
+    paths,
+    theme::DEFAULT_THEME,
+};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use toml::{Table, Value};
+
+const CONFIG_FILE: &str = "config.toml";
+const DEFAULT_QUIT_TIMES: u8 = 3;
+// 可以通过 set 修改的配置项
+pub const OPTIONS: &[&str] = &[
+    "tab_width",
+    "gutter",
+    "wrap",
+    "theme",
+    "autosave",
+    "scrolloff",
+    "quit_times",
+    "message_timeout",
+    "keymap",
+];
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub tab_width: usize,
+    pub gutter: GutterMode,
+    pub wrap: bool,
+    pub theme: String,
+    pub autosave: Option<Duration>,
+    pub scrolloff: usize,
+    pub quit_times: u8,
+    pub message_timeout: Duration,
+    pub keymap: Keymap,
+    pub preset: Preset,
+    keys: Vec<(String, String)>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            tab_width: DEFAULT_TAB_WIDTH,
+            gutter: GutterMode::default(),
+            wrap: false,
+            theme: DEFAULT_THEME.to_string(),
+            autosave: None,
+            scrolloff: 0,
+            quit_times: DEFAULT_QUIT_TIMES,
+            message_timeout: DEFAULT_DURATION,
+            keymap: Keymap::default(),
+            preset: Preset::default(),
+            keys: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    pub fn load(file: Option<&Path>) -> (Self, Vec<String>) {
+        let mut config = Self::default();
+        let mut errors = Vec::new();
+        for path in Self::paths(file) {
+            if let Ok(text) = fs::read_to_string(&path) {
+                config.apply(&path, &text, &mut errors);
+            }
+        }
+        (config, errors)
+    }
+
+    // 优先级从低到高: 系统配置, 用户配置, 项目配置
+    fn paths(file: Option<&Path>) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = paths::system_config_dirs()
+            .into_iter()
+            .rev()
+            .map(|dir| dir.join(CONFIG_FILE))
+            .collect();
+        paths.extend(paths::config_dir().map(|dir| dir.join(CONFIG_FILE)));
+        paths.extend(Self::project_path(file));
+        paths
+    }
+
+    // 从打开的文件所在目录向上查找, 找不到再从当前目录查找
+    fn project_path(file: Option<&Path>) -> Option<PathBuf> {
+        let current_dir = env::current_dir().ok()?;
+        let file_dir = file.map(|file| {
+            let dir = if file.is_dir() {
+                file
+            } else {
+                file.parent().unwrap_or(file)
+            };
+            current_dir.join(dir)
+        });
+        file_dir
+            .into_iter()
+            .chain(Some(current_dir))
+            .find_map(|start| {
+                start
+                    .ancestors()
+                    .map(|dir| dir.join(format!(".{NAME}.toml")))
+                    .find(|path| path.is_file())
+            })
+    }
+
+    fn apply(&mut self, path: &Path, text: &str, errors: &mut Vec<String>) {
+        let table = match text.parse::<Table>() {
+            Ok(table) => table,
+            Err(err) => {
+                errors.push(format!("{}: {}", path.display(), err.message()));
+                return;
+            }
+        };
+        for (key, value) in &table {
+            let result = match (key.as_str(), value) {
+                ("keys", Value::Table(keys)) => {
+                    for (sequence, command) in keys {
+                        let result = string(sequence, command)
+                            .and_then(|command| self.bind(sequence, command));
+                        if let Err(err) = result {
+                            errors.push(format!("{}: [keys] {err}", path.display()));
+                        }
+                    }
+                    Ok(())
+                }
+                _ => self.set(key, value),
+            };
+            if let Err(err) = result {
+                errors.push(format!("{}: {err}", path.display()));
+            }
+        }
+    }
+
+    fn bind(&mut self, sequence: &str, command: &str) -> Result<(), String> {
+        self.keymap.bind(sequence, command)?;
+        self.keys.push((sequence.to_string(), command.to_string()));
+        Ok(())
+    }
+
+    pub fn set(&mut self, key: &str, value: &Value) -> Result<(), String> {
+        match key {
+            "tab_width" => self.tab_width = integer(key, value)?.max(1),
+            "gutter" => {
+                let name = string(key, value)?;
+                self.gutter = GutterMode::from_name(name)
+                    .ok_or_else(|| format!("invalid value '{name}' for '{key}'"))?;
+            }
+            "wrap" => self.wrap = boolean(key, value)?,
+            "theme" => self.theme = string(key, value)?.to_string(),
+            "autosave" => {
+                let seconds = seconds(key, value)?;
+                self.autosave = (seconds > 0).then(|| Duration::from_secs(seconds));
+            }
+            "scrolloff" => self.scrolloff = integer(key, value)?,
+            "quit_times" => {
+                self.quit_times = u8::try_from(integer(key, value)?)
+                    .map_err(|_| format!("'{key}' is too large"))?;
+            }
+            "message_timeout" => {
+                self.message_timeout = Duration::from_secs(seconds(key, value)?);
+            }
+            "keymap" => {
+                let name = string(key, value)?;
+                self.preset = Preset::from_name(name)
+                    .ok_or_else(|| format!("invalid value '{name}' for '{key}'"))?;
+                // 切换预设后重新应用之前配置的按键
+                self.keymap = Keymap::new(self.preset);
+                for (sequence, command) in &self.keys {
+                    let _ = self.keymap.bind(sequence, command);
+                }
+            }
+            "keys" => return Err(format!("'{key}' must be a table")),
+            _ => return Err(format!("unknown key '{key}'")),
+        }
+        Ok(())
+    }
+}
+
+fn integer(key: &str, value: &Value) -> Result<usize, String> {
+    value
+        .as_integer()
+        .and_then(|number| usize::try_from(number).ok())
+        .ok_or_else(|| format!("'{key}' must be a non-negative integer"))
+}
+
+fn seconds(key: &str, value: &Value) -> Result<u64, String> {
+    value
+        .as_integer()
+        .and_then(|number| u64::try_from(number).ok())
+        .ok_or_else(|| format!("'{key}' must be a number of seconds"))
+}
+
+fn boolean(key: &str, value: &Value) -> Result<bool, String> {
+    value
+        .as_bool()
+        .ok_or_else(|| format!("'{key}' must be true or false"))
+}
+
+fn string<'a>(key: &str, value: &'a Value) -> Result<&'a str, String> {
+    value
+        .as_str()
+        .ok_or_else(|| format!("'{key}' must be a string"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn project_config_next_to_file() {
+        let root = env::temp_dir().join(format!("{NAME}-config-{}", std::process::id()));
+        let nested = root.join("src").join("deep");
+        fs::create_dir_all(&nested).unwrap();
+        let config = root.join(format!(".{NAME}.toml"));
+        fs::write(&config, "wrap = true\n").unwrap();
+        let file = nested.join("main.rs");
+        assert_eq!(Config::project_path(Some(&file)), Some(config.clone()));
+        assert_eq!(Config::project_path(Some(&nested)), Some(config.clone()));
+        // 只检查项目配置这一层, 不读系统和用户配置
+        let mut loaded = Config::default();
+        let mut errors = Vec::new();
+        let text = fs::read_to_string(&config).unwrap();
+        loaded.apply(&config, &text, &mut errors);
+        assert!(errors.is_empty());
+        assert!(loaded.wrap);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,171 @@
+use std::{
+    fs,
+    io::{Error, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use crate::line::Line;
+
+#[derive(Debug, Clone)]
+pub struct DirEntry {
+    pub name: String,
+    pub is_dir: bool,
+}
+
+impl DirEntry {
+    pub fn label(&self) -> String {
+        if self.is_dir {
+            format!("{}/", self.name)
+        } else {
+            self.name.clone()
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Directory {
+    pub path: PathBuf,
+    pub entries: Vec<DirEntry>,
+}
+
+impl Directory {
+    pub fn read(path: &Path) -> Result<Self, Error> {
+        let path = fs::canonicalize(path)?;
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(&path)? {
+            let entry = entry?;
+            entries.push(DirEntry {
+                name: entry.file_name().to_string_lossy().to_string(),
+                is_dir: entry.file_type()?.is_dir(),
+            });
+        }
+        // 目录排在文件前面
+        entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+        if path.parent().is_some() {
+            entries.insert(
+                0,
+                DirEntry {
+                    name: String::from(".."),
+                    is_dir: true,
+                },
+            );
+        }
+        Ok(Self { path, entries })
+    }
+
+    pub fn lines(&self) -> Vec<Line> {
+        self.entries
+            .iter()
+            .map(|entry| Line::from(&entry.label()))
+            .collect()
+    }
+
+    pub fn entry(&self, index: usize) -> Option<&DirEntry> {
+        self.entries.get(index)
+    }
+
+    pub fn entry_path(&self, index: usize) -> Option<PathBuf> {
+        self.entry(index).map(|entry| {
+            if entry.name == ".." {
+                self.path
+                    .parent()
+                    .map_or_else(|| self.path.clone(), Path::to_path_buf)
+            } else {
+                self.path.join(&entry.name)
+            }
+        })
+    }
+
+    pub fn create(&self, name: &str) -> Result<(), Error> {
+        // 只有结尾的一个 / 表示新建目录
+        let (name, is_dir) = name
+            .strip_suffix('/')
+            .map_or((name, false), |name| (name, true));
+        let target = self.path.join(check_name(name)?);
+        if is_dir {
+            fs::create_dir(target)
+        } else {
+            fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(target)
+                .map(|_| ())
+        }
+    }
+
+    pub fn rename(&self, index: usize, new_name: &str) -> Result<(), Error> {
+        match self.entry(index) {
+            Some(entry) if entry.name != ".." => {
+                let target = self.path.join(check_name(new_name)?);
+                fs::rename(self.path.join(&entry.name), target)
+            }
+            _ => Err(Error::other("Cannot rename this entry")),
+        }
+    }
+
+    pub fn delete(&self, index: usize) -> Result<(), Error> {
+        match self.entry(index) {
+            Some(entry) if entry.name != ".." => {
+                let target = self.path.join(&entry.name);
+                if entry.is_dir {
+                    fs::remove_dir_all(target)
+                } else {
+                    fs::remove_file(target)
+                }
+            }
+            _ => Err(Error::other("Cannot delete this entry")),
+        }
+    }
+}
+
+// 新名字只能是当前目录下的一项, 不能跳到别的目录
+fn check_name(name: &str) -> Result<&str, Error> {
+    if name.is_empty() || name == "." || name == ".." || name.contains(std::path::is_separator) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("invalid name '{name}'"),
+        ));
+    }
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::NAME;
+    use std::env;
+
+    #[test]
+    fn names_stay_in_directory() {
+        assert_eq!(check_name("notes.txt").unwrap(), "notes.txt");
+        assert_eq!(check_name(".hidden").unwrap(), ".hidden");
+        for name in ["", ".", "..", "../x", "a/b", "/tmp/x", "a/"] {
+            assert!(check_name(name).is_err(), "{name}");
+        }
+    }
+
+    #[test]
+    fn create_and_rename_reject_paths() {
+        let root = env::temp_dir().join(format!("{NAME}-dir-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        let dir = Directory::read(&root).unwrap();
+        assert!(dir.create("../escape").is_err());
+        assert!(dir.create("a/b/c/").is_err());
+        assert!(dir.create("/").is_err());
+        dir.create("sub/").unwrap();
+        dir.create("file").unwrap();
+        assert!(root.join("sub").is_dir());
+
+        let dir = Directory::read(&root).unwrap();
+        let index = dir
+            .entries
+            .iter()
+            .position(|entry| entry.name == "file")
+            .unwrap();
+        assert!(dir.rename(index, "../moved").is_err());
+        assert!(dir.rename(index, "sub/moved").is_err());
+        dir.rename(index, "renamed").unwrap();
+        assert!(root.join("renamed").is_file());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
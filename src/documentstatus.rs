@@ -4,6 +4,12 @@ pub struct DocumentStatus {
     pub total_line: usize,
     pub filename: String,
     pub is_modified: bool,
+    pub is_readonly: bool,
+    pub follow: Option<bool>,
+    pub indent: String,
+    pub file_type: String,
+    pub pending_keys: String,
+    pub mode: String,
 }
 
 impl DocumentStatus {
@@ -14,12 +20,42 @@ impl DocumentStatus {
             String::new()
         }
     }
+    pub fn readonly_indicator_to_string(&self) -> String {
+        if self.is_readonly {
+            String::from("[RO]")
+        } else {
+            String::new()
+        }
+    }
+    pub fn follow_indicator_to_string(&self) -> String {
+        match self.follow {
+            Some(true) => String::from(" [paused]"),
+            Some(false) => String::from(" [follow]"),
+            None => String::new(),
+        }
+    }
+    pub fn pending_keys_to_string(&self) -> String {
+        if self.pending_keys.is_empty() {
+            String::new()
+        } else {
+            format!(" {}-", self.pending_keys)
+        }
+    }
+    pub fn mode_indicator_to_string(&self) -> String {
+        if self.mode.is_empty() {
+            String::new()
+        } else {
+            format!("-- {} -- ", self.mode)
+        }
+    }
     pub fn line_count_to_string(&self) -> String {
         format!("{} lines", self.total_line)
     }
     pub fn position_indicator_to_string(&self) -> String {
         format!(
-            "{}/{}",
+            "{} | {} | {}/{}",
+            self.file_type,
+            self.indent,
             self.current_line.saturating_add(1),
             self.total_line
         )
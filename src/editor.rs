@@ -1,28 +1,93 @@
 use super::terminal::Terminal;
 use crate::{
-    command::{Command, Edit, System},
+    args::Args,
+    command::{Command, Edit, System, COMMANDS},
     commandbar::CommandBar,
+    config::{Config, OPTIONS},
+    ex::{self, ExCommand},
+    filetype::FileType,
+    follow::FollowReset,
+    gutter::GutterMode,
+    keymap::{sequence_to_string, Key, Lookup, Preset},
+    killring::KillRing,
     messagebar::MessageBar,
+    pager::{strip_escape_sequences, PagerAction},
+    picker::Picker,
     position::Position,
+    recent::RecentFiles,
+    session::{Session, SessionBuffer},
     size::Size,
     statusbar::Statusbar,
+    theme::{Theme, DEFAULT_THEME},
     uicomponent::UIComponent,
-    view::View,
+    vi::{Mode, Outcome, Vi},
+    view::{Location, View},
 };
-use crossterm::event::{read, Event, KeyEvent, KeyEventKind};
+use crossterm::event::{poll, read, Event, KeyEvent, KeyEventKind};
 use std::{
-    io::Error,
+    fs,
+    io::{self, Error},
+    ops::RangeInclusive,
     panic::{set_hook, take_hook},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
 };
 pub const NAME: &str = env!("CARGO_PKG_NAME");
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
-const QUIT_TIMES: u8 = 3;
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(250);
+const HISTORY_SIZE: usize = 100;
+
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+enum PromptType {
+    #[default]
+    Save,
+    CreateEntry,
+    RenameEntry,
+    DeleteEntry,
+    SaveSession,
+    Search,
+    FileType,
+    Ex,
+}
+
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+enum PickerType {
+    #[default]
+    Recent,
+    Session,
+    Commands,
+}
+
 #[derive(Default)]
 pub struct Editor {
     pub quit: bool,
-    pub view: View,
+    views: Vec<View>,
+    active: usize,
     message_bar: MessageBar,
     command_bar: Option<CommandBar>,
+    prompt_type: PromptType,
+    picker: Option<Picker>,
+    picker_type: PickerType,
+    picker_items: Vec<String>,
+    recent: RecentFiles,
+    kill_ring: KillRing,
+    // 上次粘贴的范围和在 kill ring 中的位置, 供 yank_pop 替换
+    yank: Option<(Location, Location, usize)>,
+    last_command: Option<Command>,
+    session: Option<String>,
+    pager: bool,
+    last_search: Option<String>,
+    search_history: Vec<String>,
+    ex_history: Vec<String>,
+    gutter_mode: GutterMode,
+    soft_wrap: bool,
+    tab_width: Option<usize>,
+    theme_name: Option<String>,
+    pending_keys: Vec<Key>,
+    vi: Option<Vi>,
+    config: Config,
+    last_input: Option<Instant>,
+    theme: Theme,
     terminal_size: Size,
     pub status_bar: Statusbar,
     pub title: String,
@@ -30,7 +95,17 @@ pub struct Editor {
 }
 
 impl Editor {
-    pub fn new() -> Result<Self, Error> {
+    pub fn new(args: &Args) -> Result<Self, Error> {
+        let stdin_text = if args.stdin {
+            let text = io::read_to_string(io::stdin())?;
+            Some(if args.pager {
+                strip_escape_sequences(&text)
+            } else {
+                text
+            })
+        } else {
+            None
+        };
         let default_hook = take_hook();
         set_hook(Box::new(move |panic_info| {
             let _ = Terminal::terminate();
@@ -39,25 +114,119 @@ impl Editor {
         Terminal::initialize()?;
         let size = Terminal::size().unwrap_or_default();
         let mut editor = Self::default();
+        editor.views.push(View::default());
+        editor.recent = RecentFiles::load();
+        editor.pager = args.pager;
+        editor.tab_width = args.tab_width;
+        editor.theme_name.clone_from(&args.theme);
+        let first_file = args.files.first().map(|file| PathBuf::from(&file.path));
+        let config_errors = editor.load_config(first_file.as_deref());
         editor.resize(size);
-        let args: Vec<String> = std::env::args().collect();
-        if let Some(file_name) = args.get(1) {
-            println!("{}", file_name);
-            if editor.view.load(file_name).is_err() {
+        let mut show_help = true;
+        if let Some(name) = &args.session {
+            editor.restore_session(name);
+            show_help = false;
+        }
+        let mut first = None;
+        if let Some(text) = &stdin_text {
+            if !editor.view().is_blank() {
+                editor.views.push(editor.new_view());
+                editor.active = editor.views.len().saturating_sub(1);
+            }
+            editor.view_mut().load_text(text);
+            if let Some(location) = args.stdin_location {
                 editor
-                    .message_bar
-                    .update_message(&format!("File open error, filename {}", file_name));
+                    .view_mut()
+                    .restore_location(location, Position::default());
+            }
+            first = Some(editor.active);
+        }
+        for file in &args.files {
+            if !editor.open_file(&file.path) {
+                show_help = false;
+                continue;
             }
+            if let Some(location) = file.location {
+                editor
+                    .view_mut()
+                    .restore_location(location, Position::default());
+            }
+            first.get_or_insert(editor.active);
+        }
+        if args.readonly || args.pager {
+            for view in &mut editor.views {
+                view.set_readonly(true);
+            }
+        }
+        if let Some(index) = first {
+            editor.active = index;
+        }
+        if show_help {
+            editor.show_help();
+        }
+        if !config_errors.is_empty() {
+            editor.report_config_errors(&config_errors);
         }
-        editor
-            .message_bar
-            .update_message("HELP: Ctrl-S = save | Ctrl-Q = quit");
         editor.refresh_status();
         Ok(editor)
     }
 
+    fn view(&self) -> &View {
+        &self.views[self.active]
+    }
+
+    fn view_mut(&mut self) -> &mut View {
+        &mut self.views[self.active]
+    }
+
+    fn show_help(&mut self) {
+        if self.pager {
+            self.message_bar.update_message(
+                "PAGER: Space = next page | b = previous page | / = search | n = next match | q = quit",
+            );
+        } else if self.view().is_directory() {
+            let quit = self.key_hint(System::Quit);
+            self.message_bar.update_message(&format!(
+                "HELP: Enter = open | c = create | r = rename | d = delete | {quit} = quit"
+            ));
+        } else {
+            let save = self.key_hint(System::Save);
+            let quit = self.key_hint(System::Quit);
+            self.message_bar
+                .update_message(&format!("HELP: {save} = save | {quit} = quit"));
+        }
+    }
+
+    fn key_hint(&self, command: System) -> String {
+        self.config
+            .keymap
+            .key_for(Command::System(command))
+            .unwrap_or_else(|| String::from("(unbound)"))
+    }
+
+    fn show_readonly_message(&mut self) {
+        if self.view().is_following() {
+            let follow = self.key_hint(System::Follow);
+            self.message_bar.update_message(&format!(
+                "Buffer is read-only while following. Press {follow} at the end to stop."
+            ));
+            return;
+        }
+        let toggle = self.key_hint(System::ToggleReadonly);
+        self.message_bar.update_message(&format!(
+            "Buffer is read-only. Press {toggle} to allow editing."
+        ));
+    }
+
     pub fn refresh_status(&mut self) {
-        let status = self.view.get_status();
+        let mut status = self.view().get_status();
+        status.pending_keys = sequence_to_string(&self.pending_keys);
+        if let Some(vi) = self.vi.as_ref().filter(|_| self.is_modal_view()) {
+            status.mode = vi.mode().name().to_string();
+            if status.pending_keys.is_empty() {
+                status.pending_keys = vi.pending();
+            }
+        }
         let title = format!("{} - {NAME}", status.filename);
         self.status_bar.update_status(status);
         if title != self.title && matches!(Terminal::set_title(&title), Ok(())) {
@@ -71,8 +240,37 @@ impl Editor {
             if self.quit {
                 break;
             }
+            let following = self.views.iter().any(View::is_following);
+            let timeout = [following.then_some(FOLLOW_INTERVAL), self.autosave_delay()]
+                .into_iter()
+                .flatten()
+                .min();
+            if let Some(timeout) = timeout {
+                match poll(timeout) {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        if following {
+                            self.poll_followers();
+                        }
+                        if self.autosave_delay() == Some(Duration::ZERO) {
+                            self.autosave();
+                        }
+                        self.refresh_status();
+                        continue;
+                    }
+                    Err(err) => {
+                        #[cfg(debug_assertions)]
+                        {
+                            panic!("Could not poll event: {err:?}");
+                        }
+                    }
+                }
+            }
             match read() {
-                Ok(event) => self.evaluate_event(event),
+                Ok(event) => {
+                    self.last_input = Some(Instant::now());
+                    self.evaluate_event(event);
+                }
                 Err(err) => {
                     #[cfg(debug_assertions)]
                     {
@@ -80,15 +278,15 @@ impl Editor {
                     }
                 }
             }
-            let status = self.view.get_status();
-            self.status_bar.update_status(status);
+            self.refresh_status();
         }
     }
 
     pub fn process_command(&mut self, command: Command) {
+        let previous = self.last_command.replace(command);
         match command {
             Command::System(System::Quit) => {
-                if self.command_bar.is_none() {
+                if self.command_bar.is_none() && self.picker.is_none() {
                     self.handler_quit();
                 }
             }
@@ -96,71 +294,1096 @@ impl Editor {
             _ => self.reset_quit_times(),
         }
 
+        if self.picker.is_some() {
+            self.handler_picker_command(command);
+            return;
+        }
+
         match command {
             Command::System(System::Quit | System::Resize(_)) => {}
+            Command::System(
+                System::OpenRecent
+                | System::NextBuffer
+                | System::PreviousBuffer
+                | System::SaveSession
+                | System::OpenSession
+                | System::ToggleReadonly
+                | System::Follow
+                | System::ToggleGutter
+                | System::ToggleWrap
+                | System::Yank
+                | System::YankPop
+                | System::SetMark
+                | System::KillRegion
+                | System::CopyRegion
+                | System::SetFileType
+                | System::ReloadConfig
+                | System::CommandPalette
+                | System::CommandLine,
+            ) if self.command_bar.is_some() => {}
+            Command::System(System::CommandLine) => self.show_command_line(""),
+            Command::System(System::CommandPalette) => self.show_command_palette(),
+            Command::System(System::ReloadConfig) => self.reload_config(),
+            Command::System(System::Yank) => self.yank(),
+            Command::System(System::YankPop) => {
+                if matches!(
+                    previous,
+                    Some(Command::System(System::Yank | System::YankPop))
+                ) {
+                    self.yank_pop();
+                } else {
+                    self.message_bar
+                        .update_message("Previous command was not a yank.");
+                }
+            }
+            Command::System(System::SetMark) => {
+                if self.is_modal_view() {
+                    let message = if self.view_mut().set_mark() {
+                        "Mark set."
+                    } else {
+                        "Mark deactivated."
+                    };
+                    self.message_bar.update_message(message);
+                }
+            }
+            Command::System(System::KillRegion) => {
+                self.kill_region(previous.is_some_and(Command::is_kill));
+            }
+            Command::System(System::CopyRegion) => self.copy_region(),
+            Command::System(System::SetFileType) => {
+                if !self.view().is_directory() {
+                    self.show_prompt(PromptType::FileType, "File type: ".to_string());
+                }
+            }
+            Command::System(System::ToggleWrap) => {
+                self.soft_wrap = !self.soft_wrap;
+                let soft_wrap = self.soft_wrap;
+                for view in &mut self.views {
+                    view.set_soft_wrap(soft_wrap);
+                }
+                self.message_bar.update_message(if soft_wrap {
+                    "Soft wrap: on"
+                } else {
+                    "Soft wrap: off"
+                });
+            }
+            Command::System(System::ToggleGutter) => {
+                self.gutter_mode = self.gutter_mode.next();
+                let mode = self.gutter_mode;
+                for view in &mut self.views {
+                    view.set_gutter_mode(mode);
+                }
+                self.message_bar
+                    .update_message(&format!("Line numbers: {}", mode.name()));
+            }
+            Command::System(System::Follow) => self.toggle_follow(),
+            Command::System(System::ToggleReadonly) => self.toggle_readonly(),
+            Command::System(System::OpenRecent) => self.show_recent_picker(),
+            Command::System(System::NextBuffer) => {
+                self.switch_buffer(self.active.saturating_add(1) % self.views.len());
+            }
+            Command::System(System::PreviousBuffer) => {
+                let len = self.views.len();
+                self.switch_buffer(self.active.saturating_add(len).saturating_sub(1) % len);
+            }
+            Command::System(System::SaveSession) => {
+                let name = self.session.clone().unwrap_or_default();
+                self.show_prompt(PromptType::SaveSession, "Save session as: ".to_string());
+                if let Some(command_bar) = &mut self.command_bar {
+                    command_bar.set_value(&name);
+                }
+            }
+            Command::System(System::OpenSession) => self.show_session_picker(),
             Command::System(System::Dismiss) => {
                 if self.command_bar.is_some() {
                     self.dimiss_prompt();
-                    self.message_bar.update_message("File save abort!");
+                    let message = match self.prompt_type {
+                        PromptType::Save => "File save abort!",
+                        PromptType::CreateEntry => "Create aborted.",
+                        PromptType::RenameEntry => "Rename aborted.",
+                        PromptType::DeleteEntry => "Delete aborted.",
+                        PromptType::SaveSession => "Session save aborted.",
+                        PromptType::Search => "Search aborted.",
+                        PromptType::FileType => "File type unchanged.",
+                        PromptType::Ex => "",
+                    };
+                    self.message_bar.update_message(message);
+                } else {
+                    self.view_mut().clear_mark();
                 }
             }
             Command::System(System::Save) => {
-                if self.command_bar.is_none() {
+                if self.command_bar.is_none() && !self.pager {
                     self.handler_save()
                 }
             }
             Command::Move(direction) => {
-                if self.command_bar.is_none() {
-                    self.view.move_text_location(direction)
+                if let Some(command_bar) = &mut self.command_bar {
+                    command_bar.handle_move(direction);
+                } else {
+                    self.view_mut().move_text_location(direction)
                 }
             }
             Command::Edit(edit) => {
                 if let Some(command_bar) = &mut self.command_bar {
                     if matches!(edit, Edit::InsertNewline) {
-                        let filename = command_bar.value();
+                        let value = command_bar.value();
                         self.dimiss_prompt();
-                        self.save(Some(filename));
+                        self.handler_prompt(value);
                     } else {
                         command_bar.handle_command_edit(edit);
                     }
+                } else if self.pager {
+                    self.handler_pager_edit(edit);
+                } else if self.view().is_directory() {
+                    self.handler_directory_edit(edit);
+                } else if self.vi.as_ref().is_some_and(|vi| vi.mode() != Mode::Insert)
+                    && matches!(edit, Edit::Insert(_) | Edit::InsertNewline)
+                {
+                    // 普通模式下不插入文本
+                } else if self.view().is_readonly() {
+                    self.show_readonly_message();
+                } else if let Some(killed) = self.view_mut().handler_edit(edit) {
+                    if previous.is_some_and(Command::is_kill) {
+                        self.kill_ring.append(killed, edit.is_backward_kill());
+                    } else {
+                        self.kill_ring.push(killed);
+                    }
+                }
+            }
+        }
+    }
+
+    fn handler_prompt(&mut self, value: String) {
+        match self.prompt_type {
+            PromptType::Save => self.save(Some(value)),
+            PromptType::CreateEntry => {
+                if value.is_empty() {
+                    return;
+                }
+                match self.view_mut().create_entry(&value) {
+                    Ok(()) => self.message_bar.update_message(&format!("Created {value}")),
+                    Err(err) => self
+                        .message_bar
+                        .update_message(&format!("Could not create {value}: {err}")),
+                }
+            }
+            PromptType::RenameEntry => {
+                if value.is_empty() {
+                    return;
+                }
+                match self.view_mut().rename_entry(&value) {
+                    Ok(()) => self
+                        .message_bar
+                        .update_message(&format!("Renamed to {value}")),
+                    Err(err) => self
+                        .message_bar
+                        .update_message(&format!("Could not rename: {err}")),
+                }
+            }
+            PromptType::DeleteEntry => {
+                if !value.eq_ignore_ascii_case("y") {
+                    self.message_bar.update_message("Delete aborted.");
+                    return;
+                }
+                match self.view_mut().delete_entry() {
+                    Ok(()) => self.message_bar.update_message("Deleted."),
+                    Err(err) => self
+                        .message_bar
+                        .update_message(&format!("Could not delete: {err}")),
+                }
+            }
+            PromptType::SaveSession => match self.save_session(&value) {
+                Ok(()) => self
+                    .message_bar
+                    .update_message(&format!("Session {value} saved.")),
+                Err(err) => self
+                    .message_bar
+                    .update_message(&format!("Could not save session: {err}")),
+            },
+            PromptType::Search => {
+                if !value.is_empty() {
+                    push_history(&mut self.search_history, &value);
+                    self.last_search = Some(value);
+                }
+                self.search_next();
+            }
+            PromptType::Ex => self.run_ex(&value),
+            PromptType::FileType => {
+                // 留空则重新自动检测
+                let file_type = if value.trim().is_empty() {
+                    None
+                } else if let Some(file_type) = FileType::from_name(&value) {
+                    Some(file_type)
+                } else {
+                    let names: Vec<&str> = FileType::ALL.iter().map(|ft| ft.name()).collect();
+                    self.message_bar.update_message(&format!(
+                        "Unknown file type {value}. Known types: {}",
+                        names.join(", ")
+                    ));
+                    return;
+                };
+                let file_type = self.view_mut().set_file_type(file_type);
+                self.message_bar
+                    .update_message(&format!("File type: {}", file_type.name()));
+            }
+        }
+    }
+
+    fn show_command_line(&mut self, value: &str) {
+        if self.pager {
+            return;
+        }
+        self.show_prompt(PromptType::Ex, ":".to_string());
+        if let Some(command_bar) = &mut self.command_bar {
+            command_bar.set_value(value);
+            command_bar.set_history(self.ex_history.clone());
+            command_bar.set_completer(ex::complete);
+        }
+    }
+
+    fn run_ex(&mut self, input: &str) {
+        if input.trim().is_empty() {
+            return;
+        }
+        push_history(&mut self.ex_history, input);
+        let current = self.view().text_location().line_index;
+        let last = self.view().line_count().saturating_sub(1);
+        let command = match ex::parse(input, current, last) {
+            Ok(command) => command,
+            Err(err) => {
+                self.message_bar.update_message(&err);
+                return;
+            }
+        };
+        match command {
+            ExCommand::Goto(line_index) => self.view_mut().set_text_location(Location {
+                line_index,
+                grapheme_index: 0,
+            }),
+            ExCommand::Write(None) => self.handler_save(),
+            ExCommand::Write(Some(name)) => {
+                if self.check_editable() {
+                    self.save(Some(name));
+                }
+            }
+            ExCommand::WriteQuit => {
+                self.handler_save();
+                if self.view().is_file_loaded()
+                    && !self.view().is_modified()
+                    && !self.view().is_directory()
+                {
+                    self.handler_quit();
+                }
+            }
+            ExCommand::Quit { force } => {
+                if force || !self.has_unsaved_changes() {
+                    self.quit = true;
                 } else {
-                    self.view.handler_edit(edit)
+                    self.message_bar
+                        .update_message("No write since last change (add ! to override)");
+                }
+            }
+            ExCommand::Edit(filename) => {
+                if self.open_file(&filename) {
+                    self.show_help();
                 }
             }
+            ExCommand::Delete(lines) => {
+                if self.check_editable() {
+                    let killed = self.view_mut().delete_lines(*lines.start(), *lines.end());
+                    self.kill_ring.push(killed);
+                }
+            }
+            ExCommand::Substitute {
+                lines,
+                pattern,
+                replacement,
+                global,
+            } => {
+                if self.check_editable() {
+                    self.substitute(lines, &pattern, &replacement, global);
+                }
+            }
+            ExCommand::Set(args) => self.set_options(&args),
         }
     }
 
-    pub fn evaluate_event(&mut self, ev: Event) {
-        let should_handler = match ev {
-            Event::Key(KeyEvent { kind, .. }) => kind == KeyEventKind::Press,
-            Event::Resize(_, _) => true,
-            _ => false,
+    fn check_editable(&mut self) -> bool {
+        if self.pager || self.view().is_directory() {
+            self.message_bar
+                .update_message("This buffer cannot be edited.");
+            false
+        } else if self.view().is_readonly() {
+            self.show_readonly_message();
+            false
+        } else {
+            true
+        }
+    }
+
+    fn substitute(
+        &mut self,
+        lines: RangeInclusive<usize>,
+        pattern: &str,
+        replacement: &str,
+        global: bool,
+    ) {
+        let mut count: usize = 0;
+        let mut changed: usize = 0;
+        let mut last_changed = None;
+        for line_index in lines {
+            let Some(text) = self.view().line(line_index).map(ToString::to_string) else {
+                break;
+            };
+            if let Some((text, replaced)) = ex::substitute(&text, pattern, replacement, global) {
+                self.view_mut().replace_line(line_index, &text);
+                count = count.saturating_add(replaced);
+                changed = changed.saturating_add(1);
+                last_changed = Some(line_index);
+            }
+        }
+        let Some(line_index) = last_changed else {
+            self.message_bar
+                .update_message(&format!("Pattern not found: {pattern}"));
+            return;
+        };
+        self.view_mut().set_text_location(Location {
+            line_index,
+            grapheme_index: 0,
+        });
+        if changed > 1 {
+            self.message_bar
+                .update_message(&format!("{count} substitutions on {changed} lines"));
+        }
+    }
+
+    fn set_options(&mut self, args: &[String]) {
+        if args.is_empty() {
+            self.message_bar
+                .update_message(&format!("Options: {}", OPTIONS.join(", ")));
+            return;
+        }
+        let mut config = self.config.clone();
+        for arg in args {
+            let (key, value) = ex::option_value(arg);
+            if let Err(err) = config.set(&key, &value) {
+                self.message_bar.update_message(&err);
+                return;
+            }
+        }
+        let errors = self.apply_config(config);
+        let config = self.config.clone();
+        for view in &mut self.views {
+            apply_view_options(view, &config);
+        }
+        if errors.is_empty() {
+            self.message_bar.update_message(&args.join(" "));
+        } else {
+            self.report_config_errors(&errors);
+        }
+    }
+
+    fn handler_pager_edit(&mut self, edit: Edit) {
+        let Ok(action) = PagerAction::try_from(edit) else {
+            return;
+        };
+        match action {
+            PagerAction::Move(mv) => self.view_mut().move_text_location(mv),
+            PagerAction::Top => self.view_mut().jump_to_line(0),
+            PagerAction::Bottom => self.view_mut().jump_to_line(usize::MAX),
+            PagerAction::Search => {
+                self.show_prompt(PromptType::Search, "/".to_string());
+                if let Some(command_bar) = &mut self.command_bar {
+                    command_bar.set_history(self.search_history.clone());
+                }
+            }
+            PagerAction::SearchNext => self.search_next(),
+            PagerAction::Follow => self.toggle_follow(),
+            PagerAction::Quit => self.quit = true,
+        }
+    }
+
+    fn toggle_follow(&mut self) {
+        if self.view().is_directory() {
+            return;
+        }
+        if !self.view().is_following() {
+            let follow = self.key_hint(System::Follow);
+            match self.view_mut().start_follow() {
+                Ok(()) => self.message_bar.update_message(&format!(
+                    "Following file. Move up to pause, {follow} to resume."
+                )),
+                Err(err) => self
+                    .message_bar
+                    .update_message(&format!("Could not follow file: {err}")),
+            }
+        } else if !self.view().is_at_bottom() {
+            self.view_mut().jump_to_line(usize::MAX);
+            self.message_bar.update_message("Following resumed.");
+        } else {
+            self.view_mut().stop_follow();
+            self.message_bar.update_message("Stopped following file.");
+        }
+    }
+
+    fn poll_followers(&mut self) {
+        let mut reset = None;
+        for view in &mut self.views {
+            reset = view.poll_follow().or(reset);
+        }
+        match reset {
+            Some(FollowReset::Truncated) => self.message_bar.update_message("File truncated."),
+            Some(FollowReset::Rotated) => self.message_bar.update_message("File rotated."),
+            None => {}
+        }
+    }
+
+    fn yank(&mut self) {
+        if self.pager || self.view().is_directory() {
+            return;
+        }
+        if self.view().is_readonly() {
+            self.show_readonly_message();
+            return;
+        }
+        match self.kill_ring.latest().map(str::to_string) {
+            Some(text) => {
+                let start = self.view().text_location();
+                self.view_mut().insert_text(&text);
+                self.yank = Some((start, self.view().text_location(), 0));
+            }
+            None => self.message_bar.update_message("Kill ring is empty."),
+        }
+    }
+
+    // 把刚粘贴的文本换成 kill ring 里更早的一条
+    fn yank_pop(&mut self) {
+        let Some((start, end, index)) = self.yank else {
+            return;
+        };
+        if self.view().is_readonly() {
+            self.show_readonly_message();
+            return;
+        }
+        let index = index.saturating_add(1);
+        let Some(text) = self.kill_ring.get(index).map(str::to_string) else {
+            return;
+        };
+        self.view_mut().delete_range(start, end);
+        self.view_mut().insert_text(&text);
+        self.yank = Some((start, self.view().text_location(), index));
+    }
+
+    fn kill_region(&mut self, append: bool) {
+        if !self.is_modal_view() {
+            return;
+        }
+        let Some((start, end)) = self.view().region() else {
+            self.message_bar.update_message("The mark is not set.");
+            return;
+        };
+        if self.view().is_readonly() {
+            self.show_readonly_message();
+            return;
+        }
+        let killed = self.view_mut().delete_range(start, end);
+        if append {
+            self.kill_ring.append(killed, false);
+        } else {
+            self.kill_ring.push(killed);
+        }
+    }
+
+    fn copy_region(&mut self) {
+        if !self.is_modal_view() {
+            return;
+        }
+        let Some((start, end)) = self.view().region() else {
+            self.message_bar.update_message("The mark is not set.");
+            return;
         };
-        if should_handler {
-            if let Ok(command) = Command::try_from(ev) {
+        let text = self.view().text_range(start, end);
+        self.kill_ring.push(text);
+        self.view_mut().clear_mark();
+        self.message_bar.update_message("Region copied.");
+    }
+
+    fn toggle_readonly(&mut self) {
+        if self.pager || self.view().is_directory() {
+            return;
+        }
+        if self.view().is_following() {
+            self.show_readonly_message();
+            return;
+        }
+        let readonly = !self.view().is_readonly();
+        self.view_mut().set_readonly(readonly);
+        if readonly {
+            self.message_bar.update_message("Buffer is now read-only.");
+        } else {
+            self.message_bar.update_message("Editing enabled.");
+        }
+    }
+
+    fn search_next(&mut self) {
+        let Some(query) = self.last_search.clone() else {
+            return;
+        };
+        if !self.view_mut().search_next(&query) {
+            self.message_bar
+                .update_message(&format!("Pattern not found: {query}"));
+        }
+    }
+
+    fn handler_directory_edit(&mut self, edit: Edit) {
+        match edit {
+            Edit::InsertNewline => self.open_selected_entry(),
+            Edit::Insert('c') => self.show_prompt(
+                PromptType::CreateEntry,
+                "New file (end with / for directory): ".to_string(),
+            ),
+            Edit::Insert('r') => {
+                if let Some(entry) = self.view().selected_entry().filter(|e| e.name != "..") {
+                    let prompt = format!("Rename {} to: ", entry.name);
+                    self.show_prompt(PromptType::RenameEntry, prompt);
+                }
+            }
+            Edit::Insert('d') => {
+                if let Some(entry) = self.view().selected_entry().filter(|e| e.name != "..") {
+                    let prompt = format!("Delete {}? (y/n): ", entry.label());
+                    self.show_prompt(PromptType::DeleteEntry, prompt);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn open_selected_entry(&mut self) {
+        if let Some(path) = self.view().selected_entry_path() {
+            if self.open_file(&path.to_string_lossy()) {
+                self.show_help();
+            }
+        }
+    }
+
+    fn open_file(&mut self, filename: &str) -> bool {
+        let target = fs::canonicalize(filename).ok();
+        if let Some(index) = self.views.iter().position(|view| {
+            !view.is_directory()
+                && view.file_path().and_then(|p| fs::canonicalize(p).ok()) == target
+                && target.is_some()
+        }) {
+            self.switch_buffer(index);
+            return true;
+        }
+        self.remember_location(self.active);
+        self.reset_vi();
+        let reuse = self.view().is_blank() || self.view().is_directory();
+        let mut view = self.new_view();
+        if view.load(filename).is_err() {
+            self.message_bar
+                .update_message(&format!("File open error, filename {filename}"));
+            return false;
+        }
+        if !view.is_directory() {
+            if let Some(path) = view.file_path() {
+                let (location, scroll_offset) = self
+                    .recent
+                    .get(path)
+                    .map(|entry| (entry.location, entry.scroll_offset))
+                    .unwrap_or_default();
+                view.restore_location(location, scroll_offset);
+            }
+        }
+        if reuse {
+            *self.view_mut() = view;
+        } else {
+            self.views.push(view);
+            self.active = self.views.len().saturating_sub(1);
+        }
+        let errors = self.configure_view(self.active);
+        if !errors.is_empty() {
+            self.report_config_errors(&errors);
+        }
+        self.remember_location(self.active);
+        true
+    }
+
+    fn load_config(&mut self, file: Option<&Path>) -> Vec<String> {
+        let (mut config, mut errors) = Config::load(file);
+        self.override_config(&mut config);
+        errors.extend(self.apply_config(config));
+        for err in self.configure_views() {
+            if !errors.contains(&err) {
+                errors.push(err);
+            }
+        }
+        errors
+    }
+
+    // 命令行参数优先于配置文件
+    fn override_config(&self, config: &mut Config) {
+        if let Some(tab_width) = self.tab_width {
+            config.tab_width = tab_width;
+        }
+        if let Some(name) = &self.theme_name {
+            config.theme.clone_from(name);
+        }
+    }
+
+    fn configure_views(&mut self) -> Vec<String> {
+        let mut errors: Vec<String> = Vec::new();
+        for index in 0..self.views.len() {
+            for err in self.configure_view(index) {
+                if !errors.contains(&err) {
+                    errors.push(err);
+                }
+            }
+        }
+        errors
+    }
+
+    // 缓冲区选项取自文件所在项目的配置, 没有文件时用全局配置
+    fn configure_view(&mut self, index: usize) -> Vec<String> {
+        let Some(file) = self
+            .views
+            .get(index)
+            .and_then(View::file_path)
+            .map(Path::to_path_buf)
+        else {
+            let config = self.config.clone();
+            if let Some(view) = self.views.get_mut(index) {
+                apply_view_options(view, &config);
+            }
+            return Vec::new();
+        };
+        let (mut config, errors) = Config::load(Some(&file));
+        self.override_config(&mut config);
+        if let Some(view) = self.views.get_mut(index) {
+            apply_view_options(view, &config);
+        }
+        errors
+    }
+
+    fn apply_config(&mut self, config: Config) -> Vec<String> {
+        let mut errors = Vec::new();
+        let theme = Theme::load(&config.theme).unwrap_or_else(|err| {
+            errors.push(format!("could not load theme: {err}"));
+            Theme::load(DEFAULT_THEME).unwrap_or_default()
+        });
+        self.gutter_mode = config.gutter;
+        self.soft_wrap = config.wrap;
+        self.message_bar.set_duration(config.message_timeout);
+        if config.preset == Preset::Vi {
+            self.vi.get_or_insert_with(Vi::default);
+        } else if self.vi.take().is_some() {
+            for view in &mut self.views {
+                view.set_selection(None);
+            }
+        }
+        self.config = config;
+        self.set_theme(theme);
+        errors
+    }
+
+    fn reload_config(&mut self) {
+        let file = self.view().file_path().map(Path::to_path_buf);
+        let errors = self.load_config(file.as_deref());
+        if errors.is_empty() {
+            self.message_bar.update_message("Configuration reloaded.");
+        } else {
+            self.report_config_errors(&errors);
+        }
+    }
+
+    fn report_config_errors(&mut self, errors: &[String]) {
+        self.message_bar
+            .update_message(&format!("Config error: {}", errors.join("; ")));
+    }
+
+    // 距离上次按键满 autosave 秒后才保存
+    fn autosave_delay(&self) -> Option<Duration> {
+        let interval = self.config.autosave.filter(|_| !self.pager)?;
+        let pending = self
+            .views
+            .iter()
+            .any(|view| view.is_file_loaded() && view.is_modified() && !view.is_readonly());
+        if !pending {
+            return None;
+        }
+        let elapsed = self.last_input.map_or(interval, |time| time.elapsed());
+        Some(interval.saturating_sub(elapsed))
+    }
+
+    fn autosave(&mut self) {
+        self.last_input = Some(Instant::now());
+        let mut saved: usize = 0;
+        for view in &mut self.views {
+            if view.is_file_loaded()
+                && view.is_modified()
+                && !view.is_readonly()
+                && !view.is_directory()
+                && view.save().is_ok()
+            {
+                saved = saved.saturating_add(1);
+            }
+        }
+        if saved > 0 {
+            self.message_bar
+                .update_message(&format!("Autosaved {saved} file(s)."));
+        }
+    }
+
+    fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+        for view in &mut self.views {
+            view.set_theme(theme);
+        }
+        self.status_bar.set_theme(theme);
+        self.message_bar.set_theme(theme);
+    }
+
+    fn new_view(&self) -> View {
+        let mut view = View::default();
+        view.set_theme(self.theme);
+        view.set_gutter_mode(self.gutter_mode);
+        view.set_soft_wrap(self.soft_wrap);
+        view.set_tab_width(self.config.tab_width);
+        view.set_scrolloff(self.config.scrolloff);
+        view.resize(self.view_size());
+        view
+    }
+
+    fn switch_buffer(&mut self, index: usize) {
+        if index == self.active || index >= self.views.len() {
+            return;
+        }
+        self.remember_location(self.active);
+        self.reset_vi();
+        self.active = index;
+        self.view_mut().mark_redraw(true);
+        let name = self.view().get_status().filename;
+        self.message_bar.update_message(&format!(
+            "Buffer {}/{}: {name}",
+            index.saturating_add(1),
+            self.views.len()
+        ));
+    }
+
+    fn remember_location(&mut self, index: usize) {
+        let Some(view) = self.views.get(index) else {
+            return;
+        };
+        if view.is_directory() {
+            return;
+        }
+        if let Some(path) = view.file_path() {
+            self.recent
+                .touch(path, view.text_location(), view.scroll_offset());
+            let _ = self.recent.save();
+        }
+    }
+
+    // 退出时记住每个缓冲区的位置, 并保存当前会话
+    fn save_state(&mut self) {
+        for index in 0..self.views.len() {
+            self.remember_location(index);
+        }
+        if let Some(name) = self.session.clone() {
+            let _ = self.save_session(&name);
+        }
+    }
+
+    fn save_session(&mut self, name: &str) -> Result<(), Error> {
+        let mut session = Session {
+            search_history: self.search_history.clone(),
+            ..Session::default()
+        };
+        for (index, view) in self.views.iter().enumerate() {
+            if index == self.active {
+                session.active = session.buffers.len();
+            }
+            let mut buffer = SessionBuffer {
+                location: view.text_location(),
+                scroll_offset: view.scroll_offset(),
+                ..SessionBuffer::default()
+            };
+            if let Some(path) = view.file_path() {
+                buffer.path = Some(fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()));
+            } else if !view.is_blank() {
+                buffer.scratch = Some(view.contents());
+            } else {
+                continue;
+            }
+            session.buffers.push(buffer);
+        }
+        session.save(name)?;
+        self.session = Some(name.to_string());
+        Ok(())
+    }
+
+    fn restore_session(&mut self, name: &str) -> bool {
+        // 会话只记录文件路径, 文件里未保存的修改会丢失
+        if self.has_unsaved_changes() {
+            self.message_bar
+                .update_message("WARNING! Unsaved changes. Save them before restoring a session.");
+            return false;
+        }
+        if let Some(current) = self.session.clone() {
+            if self.save_session(&current).is_err() {
+                self.message_bar
+                    .update_message(&format!("Could not save session {current}"));
+                return false;
+            }
+        }
+        let session = match Session::load(name) {
+            Ok(session) => session,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                if let Err(err) = self.save_session(name) {
+                    self.message_bar
+                        .update_message(&format!("Could not save session {name}: {err}"));
+                    return false;
+                }
+                self.message_bar
+                    .update_message(&format!("Started new session {name}"));
+                return true;
+            }
+            Err(err) => {
+                self.message_bar
+                    .update_message(&format!("Could not load session {name}: {err}"));
+                return false;
+            }
+        };
+        self.remember_location(self.active);
+        let mut views = Vec::new();
+        for buffer in &session.buffers {
+            let mut view = self.new_view();
+            if let Some(scratch) = &buffer.scratch {
+                view.load_scratch(scratch);
+            } else if let Some(path) = &buffer.path {
+                if view.load(&path.to_string_lossy()).is_err() {
+                    continue;
+                }
+            }
+            view.restore_location(buffer.location, buffer.scroll_offset);
+            views.push(view);
+        }
+        if views.is_empty() {
+            views.push(self.new_view());
+        }
+        self.active = session.active.min(views.len().saturating_sub(1));
+        self.views = views;
+        self.last_search = session.search_history.last().cloned();
+        self.search_history = session.search_history;
+        self.session = Some(name.to_string());
+        self.message_bar
+            .update_message(&format!("Session {name} restored."));
+        let errors = self.configure_views();
+        if !errors.is_empty() {
+            self.report_config_errors(&errors);
+        }
+        true
+    }
+
+    fn show_recent_picker(&mut self) {
+        let items = self
+            .recent
+            .entries()
+            .iter()
+            .map(|entry| entry.path.display().to_string())
+            .collect();
+        self.show_picker(PickerType::Recent, "Recent files: ", items);
+    }
+
+    fn show_session_picker(&mut self) {
+        self.show_picker(PickerType::Session, "Sessions: ", Session::list());
+    }
+
+    fn show_command_palette(&mut self) {
+        let bindings: Vec<String> = COMMANDS
+            .iter()
+            .map(|(_, _, command)| self.config.keymap.keys_for(*command).join(", "))
+            .collect();
+        let name_width = COMMANDS
+            .iter()
+            .map(|(name, _, _)| name.len())
+            .max()
+            .unwrap_or(0);
+        let description_width = COMMANDS
+            .iter()
+            .map(|(_, description, _)| description.len())
+            .max()
+            .unwrap_or(0);
+        let items = COMMANDS
+            .iter()
+            .zip(bindings)
+            .map(|((name, description, _), keys)| {
+                format!("{name:name_width$}  {description:description_width$}  {keys}")
+                    .trim_end()
+                    .to_string()
+            })
+            .collect();
+        self.show_picker(PickerType::Commands, "Command: ", items);
+    }
+
+    fn show_picker(&mut self, picker_type: PickerType, title: &str, items: Vec<String>) {
+        let mut picker = Picker::new(title, items.clone(), self.theme);
+        picker.resize(self.view_size());
+        self.picker = Some(picker);
+        self.picker_type = picker_type;
+        self.picker_items = items;
+    }
+
+    fn handler_picker_command(&mut self, command: Command) {
+        let Some(picker) = &mut self.picker else {
+            return;
+        };
+        match command {
+            Command::System(System::Dismiss) => self.dismiss_picker(),
+            Command::Move(mv) => picker.handle_move(mv),
+            Command::Edit(Edit::InsertNewline) => {
+                let selected = picker.selected_item();
+                self.dismiss_picker();
+                let Some(index) = selected else {
+                    return;
+                };
+                let item = self.picker_items.get(index).cloned().unwrap_or_default();
+                match self.picker_type {
+                    PickerType::Recent => {
+                        if self.open_file(&item) {
+                            self.show_help();
+                        }
+                    }
+                    PickerType::Session => {
+                        self.restore_session(&item);
+                    }
+                    PickerType::Commands => {
+                        if let Some((_, _, command)) = COMMANDS.get(index) {
+                            self.process_command(*command);
+                        }
+                    }
+                }
+            }
+            Command::Edit(edit) => picker.handle_edit(edit),
+            Command::System(_) => {}
+        }
+    }
+
+    fn dismiss_picker(&mut self) {
+        self.picker = None;
+        self.view_mut().mark_redraw(true);
+    }
+
+    pub fn evaluate_event(&mut self, ev: Event) {
+        match ev {
+            Event::Key(event) if event.kind == KeyEventKind::Press => self.handler_key(event),
+            Event::Resize(width, height) => {
+                self.process_command(Command::System(System::Resize(Size {
+                    width: usize::from(width),
+                    height: usize::from(height),
+                })));
+            }
+            _ => {}
+        }
+    }
+
+    fn is_modal_view(&self) -> bool {
+        !self.pager && !self.view().is_directory()
+    }
+
+    fn reset_vi(&mut self) {
+        if let Some(vi) = &mut self.vi {
+            vi.reset(&mut self.views[self.active]);
+        }
+    }
+
+    fn handler_key(&mut self, event: KeyEvent) {
+        let key = Key::from(event);
+        let modal = self.pending_keys.is_empty()
+            && self.command_bar.is_none()
+            && self.picker.is_none()
+            && self.is_modal_view();
+        if let Some(vi) = self.vi.as_mut().filter(|_| modal) {
+            match vi.handle_key(key, &mut self.views[self.active], &mut self.kill_ring) {
+                Outcome::Handled => {
+                    self.reset_quit_times();
+                    self.last_command = None;
+                    return;
+                }
+                Outcome::Readonly => {
+                    self.show_readonly_message();
+                    return;
+                }
+                Outcome::CommandLine(range) => {
+                    self.show_command_line(&range);
+                    return;
+                }
+                Outcome::Pass => {}
+            }
+        }
+        self.dispatch_key(key);
+        if self.command_bar.is_none() && self.picker.is_none() && self.is_modal_view() {
+            if let Some(vi) = &mut self.vi {
+                vi.sync(&mut self.views[self.active]);
+            }
+        }
+    }
+
+    fn dispatch_key(&mut self, key: Key) {
+        self.pending_keys.push(key);
+        match self.config.keymap.lookup(&self.pending_keys) {
+            Lookup::Pending => {}
+            Lookup::Command(command) => {
+                self.pending_keys.clear();
                 self.process_command(command);
             }
+            Lookup::Unbound => {
+                let keys = std::mem::take(&mut self.pending_keys);
+                if let [key] = keys.as_slice() {
+                    // 没有绑定的普通字符直接输入
+                    if let Some(ch) = key.as_char() {
+                        self.process_command(Command::Edit(Edit::Insert(ch)));
+                    }
+                } else if !key.is_escape() && !self.is_dismiss(key) {
+                    self.message_bar
+                        .update_message(&format!("{} is not bound", sequence_to_string(&keys)));
+                }
+            }
         }
     }
 
+    // 取消键中断未完成的组合键
+    fn is_dismiss(&self, key: Key) -> bool {
+        matches!(
+            self.config.keymap.lookup(&[key]),
+            Lookup::Command(Command::System(System::Dismiss))
+        )
+    }
+
     pub fn dimiss_prompt(&mut self) {
         self.command_bar = None;
         self.message_bar.mark_redraw(true);
     }
 
     pub fn handler_save(&mut self) {
-        if self.view.is_file_loaded() {
+        if self.view().is_directory() {
+            self.message_bar
+                .update_message("Directory listings cannot be saved.");
+        } else if self.view().is_readonly() {
+            self.show_readonly_message();
+        } else if self.view().is_file_loaded() {
             self.save(None);
         } else {
-            self.show_prompt();
+            self.show_prompt(PromptType::Save, "Save as: ".to_string());
         }
     }
 
     pub fn save(&mut self, filename: Option<String>) {
         let result = if let Some(name) = filename {
-            self.view.save_as(&name)
+            self.view_mut().save_as(&name)
         } else {
-            self.view.save()
+            self.view_mut().save()
         };
         if result.is_ok() {
             self.message_bar.update_message("File saved successfully.");
@@ -169,25 +1392,39 @@ impl Editor {
         }
     }
 
-    pub fn show_prompt(&mut self) {
+    fn show_prompt(&mut self, prompt_type: PromptType, prompt: String) {
         let mut command_bar = CommandBar::default();
-        command_bar.set_prompt("Save as: ".to_string());
+        command_bar.set_prompt(prompt);
+        command_bar.set_theme(self.theme);
         command_bar.resize(Size {
             width: self.terminal_size.width,
             height: 1,
         });
         command_bar.mark_redraw(true);
         self.command_bar = Some(command_bar);
+        self.prompt_type = prompt_type;
+    }
+
+    fn has_unsaved_changes(&self) -> bool {
+        // 已写入的会话退出时会保存未命名缓冲区的内容
+        let session_saved = self.session.as_deref().is_some_and(Session::exists);
+        self.views
+            .iter()
+            .any(|view| view.get_status().is_modified && (!session_saved || view.is_file_loaded()))
     }
 
     pub fn handler_quit(&mut self) {
-        let is_modified = self.view.get_status().is_modified;
-        if !is_modified || self.quit_times + 1 == QUIT_TIMES {
+        let is_modified = self.has_unsaved_changes();
+        if !is_modified || self.quit_times.saturating_add(1) >= self.config.quit_times {
             self.quit = true;
         } else if is_modified {
             self.message_bar.update_message(&format!(
-                "WARNING! File has unsaved changes. Press Ctrl-Q {} more times to quit.",
-                QUIT_TIMES - self.quit_times - 1
+                "WARNING! File has unsaved changes. Press {} {} more times to quit.",
+                self.key_hint(System::Quit),
+                self.config
+                    .quit_times
+                    .saturating_sub(self.quit_times)
+                    .saturating_sub(1)
             ));
             self.quit_times += 1;
         }
@@ -200,12 +1437,19 @@ impl Editor {
         }
     }
 
+    fn view_size(&self) -> Size {
+        Size {
+            height: self.terminal_size.height.saturating_sub(2),
+            width: self.terminal_size.width,
+        }
+    }
+
     fn resize(&mut self, size: Size) {
         self.terminal_size = size;
-        self.view.resize(Size {
-            height: size.height.saturating_sub(2),
-            width: size.width,
-        });
+        let view_size = self.view_size();
+        for view in &mut self.views {
+            view.resize(view_size);
+        }
         self.message_bar.resize(Size {
             height: 1,
             width: size.width,
@@ -214,6 +1458,9 @@ impl Editor {
             height: 1,
             width: size.width,
         });
+        if let Some(picker) = &mut self.picker {
+            picker.resize(view_size);
+        }
     }
 
     fn refresh_screen(&mut self) {
@@ -232,15 +1479,24 @@ impl Editor {
             self.status_bar.render(height.saturating_sub(2));
         }
         if height > 2 {
-            self.view.render(0);
+            if let Some(picker) = &mut self.picker {
+                picker.render(0);
+            } else {
+                self.view_mut().render(0);
+            }
         }
-        let new_caret = if let Some(command_bar) = &self.command_bar {
+        let new_caret = if let Some(picker) = &self.picker {
+            Position {
+                row: 0,
+                col: picker.caret_position_col(),
+            }
+        } else if let Some(command_bar) = &self.command_bar {
             Position {
                 row: bottom_bar_row,
                 col: command_bar.caret_position_col(),
             }
         } else {
-            self.view.text_location_to_postion()
+            self.view().caret_position()
         };
         let _ = Terminal::move_caret_to(&new_caret);
         let _ = Terminal::show_caret();
@@ -250,9 +1506,102 @@ impl Editor {
 
 impl Drop for Editor {
     fn drop(&mut self) {
+        if !self.views.is_empty() {
+            self.save_state();
+        }
         let _ = Terminal::terminate();
-        if self.quit {
+        if self.quit && !self.pager {
             let _ = Terminal::print("Goodbye!!!! \r\n");
         }
     }
 }
+
+fn apply_view_options(view: &mut View, config: &Config) {
+    view.set_tab_width(config.tab_width);
+    view.set_gutter_mode(config.gutter);
+    view.set_soft_wrap(config.wrap);
+    view.set_scrolloff(config.scrolloff);
+}
+
+// 重复的记录只保留最近一次
+fn push_history(history: &mut Vec<String>, entry: &str) {
+    history.retain(|existing| existing != entry);
+    history.push(entry.to_string());
+    if history.len() > HISTORY_SIZE {
+        history.remove(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, mem::ManuallyDrop};
+
+    // 测试共用一个状态目录, 不写到用户目录里
+    fn use_test_state_dir() -> PathBuf {
+        let state = env::temp_dir().join(format!("{NAME}-state-{}", std::process::id()));
+        env::set_var("XDG_STATE_HOME", &state);
+        state
+    }
+
+    #[test]
+    fn session_keeps_active_buffer() {
+        use_test_state_dir();
+        // 不运行 Drop, 测试里不能恢复终端
+        let mut editor = ManuallyDrop::new(Editor::default());
+        for text in ["first", "second", "third"] {
+            let mut view = View::default();
+            view.load_scratch(text);
+            editor.views.push(view);
+        }
+        editor.active = 1;
+        editor.session = Some(String::from("work"));
+        editor.save_state();
+        assert_eq!(editor.active, 1);
+
+        let mut restored = ManuallyDrop::new(Editor::default());
+        assert!(restored.restore_session("work"));
+        assert_eq!(restored.views.len(), 3);
+        assert_eq!(restored.active, 1);
+        assert_eq!(restored.view().contents(), "second\n");
+    }
+
+    #[test]
+    fn project_config_per_buffer() {
+        use_test_state_dir();
+        let root = env::temp_dir().join(format!("{NAME}-projects-{}", std::process::id()));
+        for (project, tab_width) in [("a", 2), ("b", 8)] {
+            let dir = root.join(project);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(
+                dir.join(format!(".{NAME}.toml")),
+                format!("tab_width = {tab_width}\n"),
+            )
+            .unwrap();
+            fs::write(dir.join("file.txt"), "\tx\n").unwrap();
+        }
+        let mut editor = ManuallyDrop::new(Editor::default());
+        editor.views.push(View::default());
+        for project in ["a", "b"] {
+            let file = root.join(project).join("file.txt");
+            assert!(editor.open_file(&file.to_string_lossy()));
+        }
+        // 每个缓冲区用自己项目的 Tab 宽度
+        let columns: Vec<usize> = editor
+            .views
+            .iter_mut()
+            .map(|view| {
+                view.restore_location(
+                    Location {
+                        line_index: 0,
+                        grapheme_index: 1,
+                    },
+                    Position::default(),
+                );
+                view.text_location_to_postion().col
+            })
+            .collect();
+        assert_eq!(columns, [2, 8]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,510 @@
+use crate::config::OPTIONS;
+use std::{fs, ops::RangeInclusive, path::Path};
+use toml::{Table, Value};
+
+// 全名和最短缩写长度, 缩写冲突时靠前的优先
+const NAMES: &[(&str, usize)] = &[
+    ("write", 1),
+    ("wq", 2),
+    ("xit", 1),
+    ("quit", 1),
+    ("edit", 1),
+    ("delete", 1),
+    ("substitute", 1),
+    ("set", 2),
+];
+
+const SUBSTITUTE_USAGE: &str = "Usage: s/pattern/replacement/[g]";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExCommand {
+    Goto(usize),
+    Write(Option<String>),
+    WriteQuit,
+    Quit {
+        force: bool,
+    },
+    Edit(String),
+    Delete(RangeInclusive<usize>),
+    Substitute {
+        lines: RangeInclusive<usize>,
+        pattern: String,
+        replacement: String,
+        global: bool,
+    },
+    Set(Vec<String>),
+}
+
+// current 和 last 是光标所在行和最后一行, 均从 0 开始
+pub fn parse(input: &str, current: usize, last: usize) -> Result<ExCommand, String> {
+    let input = input.trim().trim_start_matches(':');
+    let (range, rest) = parse_range(input, current, last);
+    let (name, rest) = split_name(rest);
+    if name.is_empty() {
+        return match (range, rest.trim()) {
+            (Some(lines), "") => Ok(ExCommand::Goto(*lines.end())),
+            _ => Err(format!("Not an editor command: {input}")),
+        };
+    }
+    let full = full_name(name).ok_or_else(|| format!("Not an editor command: {input}"))?;
+    if range.is_some() && !matches!(full, "delete" | "substitute") {
+        return Err(String::from("No range allowed"));
+    }
+    let lines = range.unwrap_or(current..=current);
+    if full == "substitute" {
+        return parse_substitute(lines, rest.trim_start());
+    }
+    let (force, args) = match rest.strip_prefix('!') {
+        Some(args) => (true, args.trim()),
+        None => (false, rest.trim()),
+    };
+    if force && full != "quit" {
+        return Err(String::from("No ! allowed"));
+    }
+    match full {
+        "write" => Ok(ExCommand::Write(
+            (!args.is_empty()).then(|| args.to_string()),
+        )),
+        "quit" | "wq" | "xit" | "delete" if !args.is_empty() => {
+            Err(format!("Trailing characters: {args}"))
+        }
+        "wq" | "xit" => Ok(ExCommand::WriteQuit),
+        "quit" => Ok(ExCommand::Quit { force }),
+        "edit" if args.is_empty() => Err(String::from("No file name")),
+        "edit" => Ok(ExCommand::Edit(args.to_string())),
+        "delete" => Ok(ExCommand::Delete(lines)),
+        _ => Ok(ExCommand::Set(
+            args.split_whitespace().map(String::from).collect(),
+        )),
+    }
+}
+
+fn full_name(name: &str) -> Option<&'static str> {
+    NAMES
+        .iter()
+        .find(|(full, shortest)| name.len() >= *shortest && full.starts_with(name))
+        .map(|(full, _)| *full)
+}
+
+fn split_name(input: &str) -> (&str, &str) {
+    let len = input
+        .find(|ch: char| !ch.is_ascii_alphabetic())
+        .unwrap_or(input.len());
+    input.split_at(len)
+}
+
+// 超出文件范围的行号按最后一行处理
+fn parse_range(input: &str, current: usize, last: usize) -> (Option<RangeInclusive<usize>>, &str) {
+    if let Some(rest) = input.strip_prefix('%') {
+        return (Some(0..=last), rest);
+    }
+    // 省略起始地址时按当前行处理, 如 ,5
+    let (start, rest) = match parse_address(input, current, last) {
+        (Some(start), rest) => (start, rest),
+        (None, rest) if rest.starts_with(',') => (current, rest),
+        _ => return (None, input),
+    };
+    let (end, rest) = match rest.strip_prefix(',') {
+        Some(rest) => {
+            let (end, rest) = parse_address(rest, current, last);
+            (end.unwrap_or(current), rest)
+        }
+        None => (start, rest),
+    };
+    let (start, end) = (start.min(last), end.min(last));
+    (Some(start.min(end)..=start.max(end)), rest)
+}
+
+// 行号从 1 开始, 也可以是 . 或 $, 后面可以跟 +N 或 -N
+fn parse_address(input: &str, current: usize, last: usize) -> (Option<usize>, &str) {
+    let (mut line, mut rest) = if let Some(rest) = input.strip_prefix('.') {
+        (Some(current), rest)
+    } else if let Some(rest) = input.strip_prefix('$') {
+        (Some(last), rest)
+    } else {
+        let (number, rest) = split_number(input);
+        (number.map(|number| number.saturating_sub(1)), rest)
+    };
+    while let Some(sign) = rest.chars().next().filter(|ch| matches!(ch, '+' | '-')) {
+        let (number, after) = split_number(&rest[1..]);
+        let base = line.unwrap_or(current);
+        let offset = number.unwrap_or(1);
+        line = Some(if sign == '+' {
+            base.saturating_add(offset)
+        } else {
+            base.saturating_sub(offset)
+        });
+        rest = after;
+    }
+    (line, rest)
+}
+
+fn split_number(input: &str) -> (Option<usize>, &str) {
+    let len = input
+        .find(|ch: char| !ch.is_ascii_digit())
+        .unwrap_or(input.len());
+    let (digits, rest) = input.split_at(len);
+    (digits.parse().ok(), rest)
+}
+
+// 模式按普通文本匹配, 用反斜杠转义分隔符
+fn parse_substitute(lines: RangeInclusive<usize>, input: &str) -> Result<ExCommand, String> {
+    let mut chars = input.chars();
+    let delimiter = chars
+        .next()
+        .filter(|ch| !ch.is_alphanumeric() && !ch.is_whitespace() && *ch != '\\')
+        .ok_or_else(|| String::from(SUBSTITUTE_USAGE))?;
+    let mut parts = Vec::new();
+    let mut part = String::new();
+    let mut escaped = false;
+    for ch in chars {
+        if escaped {
+            if ch != delimiter && ch != '\\' {
+                part.push('\\');
+            }
+            part.push(ch);
+            escaped = false;
+        } else if ch == '\\' {
+            escaped = true;
+        } else if ch == delimiter && parts.len() < 2 {
+            parts.push(std::mem::take(&mut part));
+        } else {
+            part.push(ch);
+        }
+    }
+    if escaped {
+        part.push('\\');
+    }
+    parts.push(part);
+    let mut parts = parts.into_iter();
+    let pattern = parts.next().unwrap_or_default();
+    let replacement = parts.next().unwrap_or_default();
+    let global = match parts.next().unwrap_or_default().trim() {
+        "" => false,
+        "g" => true,
+        flags => return Err(format!("Unknown flags: {flags}")),
+    };
+    if pattern.is_empty() {
+        return Err(String::from("Empty pattern"));
+    }
+    Ok(ExCommand::Substitute {
+        lines,
+        pattern,
+        replacement,
+        global,
+    })
+}
+
+// 返回替换后的文本和替换次数
+pub fn substitute(
+    text: &str,
+    pattern: &str,
+    replacement: &str,
+    global: bool,
+) -> Option<(String, usize)> {
+    if !text.contains(pattern) {
+        return None;
+    }
+    if global {
+        let count = text.matches(pattern).count();
+        Some((text.replace(pattern, replacement), count))
+    } else {
+        Some((text.replacen(pattern, replacement, 1), 1))
+    }
+}
+
+// set 的参数: key=value, key 表示 true, nokey 表示 false
+pub fn option_value(arg: &str) -> (String, Value) {
+    let Some((key, value)) = arg.split_once('=') else {
+        return match arg.strip_prefix("no").filter(|key| OPTIONS.contains(key)) {
+            Some(key) => (key.to_string(), Value::Boolean(false)),
+            None => (arg.to_string(), Value::Boolean(true)),
+        };
+    };
+    let value = value.trim();
+    // 不是合法 TOML 值时当作字符串
+    let value = format!("value = {value}")
+        .parse::<Table>()
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| Value::String(value.to_string()));
+    (key.trim().to_string(), value)
+}
+
+// 返回补全后的整行
+pub fn complete(input: &str) -> Vec<String> {
+    let range_len = input
+        .find(|ch: char| !"0123456789.,$%+-: ".contains(ch))
+        .unwrap_or(input.len());
+    let (range, rest) = input.split_at(range_len);
+    let (name, args) = split_name(rest);
+    if args.is_empty() {
+        return NAMES
+            .iter()
+            .filter(|(full, _)| full.starts_with(name))
+            .map(|(full, _)| format!("{range}{full}"))
+            .collect();
+    }
+    let Some(args) = args.strip_prefix(' ') else {
+        return Vec::new();
+    };
+    let split = args.rfind(' ').map_or(0, |index| index.saturating_add(1));
+    let (before, word) = args.split_at(split);
+    let head = format!("{range}{name} {before}");
+    let candidates = match full_name(name) {
+        Some("edit" | "write") => complete_path(word),
+        Some("set") => complete_option(word),
+        _ => Vec::new(),
+    };
+    candidates
+        .into_iter()
+        .map(|candidate| format!("{head}{candidate}"))
+        .collect()
+}
+
+fn complete_option(word: &str) -> Vec<String> {
+    let Some((key, value)) = word.split_once('=') else {
+        return OPTIONS
+            .iter()
+            .filter(|option| option.starts_with(word))
+            .map(|option| option.to_string())
+            .collect();
+    };
+    let values: &[&str] = match key {
+        "keymap" => &["default", "vi", "emacs"],
+        "gutter" => &["off", "absolute", "relative", "hybrid"],
+        "wrap" => &["true", "false"],
+        _ => &[],
+    };
+    values
+        .iter()
+        .filter(|candidate| candidate.starts_with(value))
+        .map(|candidate| format!("{key}={candidate}"))
+        .collect()
+}
+
+// 以 . 开头的文件只在明确输入 . 时列出
+fn complete_path(word: &str) -> Vec<String> {
+    let (dir, prefix) = word.split_at(word.rfind('/').map_or(0, |index| index.saturating_add(1)));
+    let path = if dir.is_empty() {
+        Path::new(".")
+    } else {
+        Path::new(dir)
+    };
+    let Ok(entries) = fs::read_dir(path) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let visible = prefix.starts_with('.') || !name.starts_with('.');
+            (visible && name.starts_with(prefix)).then(|| {
+                let suffix = if entry.path().is_dir() { "/" } else { "" };
+                format!("{dir}{name}{suffix}")
+            })
+        })
+        .collect();
+    names.sort();
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 光标在第 5 行, 文件共 20 行
+    fn parse_at(input: &str) -> Result<ExCommand, String> {
+        parse(input, 4, 19)
+    }
+
+    #[test]
+    fn goto_addresses() {
+        assert_eq!(parse_at("42"), Ok(ExCommand::Goto(19)));
+        assert_eq!(parse_at(":12"), Ok(ExCommand::Goto(11)));
+        assert_eq!(parse_at("0"), Ok(ExCommand::Goto(0)));
+        assert_eq!(parse_at("+3"), Ok(ExCommand::Goto(7)));
+        assert_eq!(parse_at("-"), Ok(ExCommand::Goto(3)));
+        assert_eq!(parse_at("$"), Ok(ExCommand::Goto(19)));
+        assert_eq!(parse_at("$-1"), Ok(ExCommand::Goto(18)));
+        assert_eq!(parse_at("."), Ok(ExCommand::Goto(4)));
+        assert!(parse_at("").is_err());
+    }
+
+    #[test]
+    fn delete_ranges() {
+        assert_eq!(parse_at("d"), Ok(ExCommand::Delete(4..=4)));
+        assert_eq!(parse_at("10,12d"), Ok(ExCommand::Delete(9..=11)));
+        assert_eq!(parse_at("%d"), Ok(ExCommand::Delete(0..=19)));
+        assert_eq!(parse_at(".,.+3d"), Ok(ExCommand::Delete(4..=7)));
+        assert_eq!(parse_at("12,10d"), Ok(ExCommand::Delete(9..=11)));
+        assert_eq!(parse_at("3,d"), Ok(ExCommand::Delete(2..=4)));
+        assert_eq!(parse_at(",8d"), Ok(ExCommand::Delete(4..=7)));
+        assert_eq!(parse_at(",d"), Ok(ExCommand::Delete(4..=4)));
+        assert_eq!(parse_at("18,99d"), Ok(ExCommand::Delete(17..=19)));
+        assert!(parse_at("d x").is_err());
+    }
+
+    #[test]
+    fn file_commands() {
+        assert_eq!(parse_at("w"), Ok(ExCommand::Write(None)));
+        assert_eq!(
+            parse_at("write notes.txt"),
+            Ok(ExCommand::Write(Some("notes.txt".to_string())))
+        );
+        assert_eq!(parse_at("wq"), Ok(ExCommand::WriteQuit));
+        assert_eq!(parse_at("x"), Ok(ExCommand::WriteQuit));
+        assert_eq!(parse_at("q"), Ok(ExCommand::Quit { force: false }));
+        assert_eq!(parse_at("q!"), Ok(ExCommand::Quit { force: true }));
+        assert_eq!(
+            parse_at("e src/main.rs"),
+            Ok(ExCommand::Edit("src/main.rs".to_string()))
+        );
+        assert!(parse_at("e").is_err());
+        assert!(parse_at("w!").is_err());
+        assert!(parse_at("wq!").is_err());
+        assert!(parse_at("e! src/main.rs").is_err());
+        assert!(parse_at("3w").is_err());
+        assert!(parse_at("frobnicate").is_err());
+    }
+
+    #[test]
+    fn abbreviations() {
+        assert!(matches!(parse_at("se wrap"), Ok(ExCommand::Set(_))));
+        assert!(matches!(parse_at("set wrap"), Ok(ExCommand::Set(_))));
+        assert!(matches!(
+            parse_at("s/a/b/"),
+            Ok(ExCommand::Substitute { .. })
+        ));
+        assert!(matches!(
+            parse_at("su/a/b/"),
+            Ok(ExCommand::Substitute { .. })
+        ));
+        assert_eq!(full_name("w"), Some("write"));
+        assert_eq!(full_name("wq"), Some("wq"));
+        assert_eq!(full_name("s"), Some("substitute"));
+        assert_eq!(full_name("se"), Some("set"));
+        assert_eq!(full_name("writes"), None);
+    }
+
+    #[test]
+    fn substitute_commands() {
+        assert_eq!(
+            parse_at("%s/a/b/g"),
+            Ok(ExCommand::Substitute {
+                lines: 0..=19,
+                pattern: "a".to_string(),
+                replacement: "b".to_string(),
+                global: true,
+            })
+        );
+        assert_eq!(
+            parse_at(r"s/a\/b/c/g"),
+            Ok(ExCommand::Substitute {
+                lines: 4..=4,
+                pattern: "a/b".to_string(),
+                replacement: "c".to_string(),
+                global: true,
+            })
+        );
+        assert_eq!(
+            parse_at(r"s#x\y#z"),
+            Ok(ExCommand::Substitute {
+                lines: 4..=4,
+                pattern: r"x\y".to_string(),
+                replacement: "z".to_string(),
+                global: false,
+            })
+        );
+        assert_eq!(
+            parse_at("s/a"),
+            Ok(ExCommand::Substitute {
+                lines: 4..=4,
+                pattern: "a".to_string(),
+                replacement: String::new(),
+                global: false,
+            })
+        );
+        assert!(parse_at("s//b/").is_err());
+        assert!(parse_at("s/a/b/x").is_err());
+        assert!(parse_at("s").is_err());
+        assert!(parse_at("s a b").is_err());
+    }
+
+    #[test]
+    fn substitute_text() {
+        assert_eq!(
+            substitute("aXa", "a", "b", false),
+            Some(("bXa".to_string(), 1))
+        );
+        assert_eq!(
+            substitute("aXa", "a", "b", true),
+            Some(("bXb".to_string(), 2))
+        );
+        assert_eq!(substitute("xyz", "a", "b", true), None);
+    }
+
+    #[test]
+    fn option_values() {
+        assert_eq!(
+            option_value("wrap"),
+            ("wrap".to_string(), Value::Boolean(true))
+        );
+        assert_eq!(
+            option_value("nowrap"),
+            ("wrap".to_string(), Value::Boolean(false))
+        );
+        // 不是已知配置项时不去掉 no 前缀
+        assert_eq!(
+            option_value("nonsense"),
+            ("nonsense".to_string(), Value::Boolean(true))
+        );
+        assert_eq!(
+            option_value("tab_width=2"),
+            ("tab_width".to_string(), Value::Integer(2))
+        );
+        assert_eq!(
+            option_value("gutter=relative"),
+            ("gutter".to_string(), Value::String("relative".to_string()))
+        );
+        assert_eq!(
+            option_value("theme=\"dark\""),
+            ("theme".to_string(), Value::String("dark".to_string()))
+        );
+    }
+
+    #[test]
+    fn complete_command_names() {
+        assert_eq!(complete("s"), ["substitute", "set"]);
+        assert_eq!(complete("3,5d"), ["3,5delete"]);
+        assert_eq!(complete("w"), ["write", "wq"]);
+        assert!(complete("zz").is_empty());
+    }
+
+    #[test]
+    fn complete_options() {
+        assert_eq!(complete("set sc"), ["set scrolloff"]);
+        assert_eq!(complete("se wrap ke"), ["se wrap keymap"]);
+        assert_eq!(complete("set keymap=e"), ["set keymap=emacs"]);
+        assert_eq!(complete("set wrap="), ["set wrap=true", "set wrap=false"]);
+    }
+
+    #[test]
+    fn complete_paths() {
+        let dir = std::env::temp_dir().join(format!("ex-complete-{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("alpha.txt"), "").unwrap();
+        fs::write(dir.join(".hidden"), "").unwrap();
+        let prefix = format!("{}/", dir.display());
+        assert_eq!(
+            complete(&format!("e {prefix}")),
+            [format!("e {prefix}alpha.txt"), format!("e {prefix}sub/")]
+        );
+        assert_eq!(
+            complete(&format!("w {prefix}.h")),
+            [format!("w {prefix}.hidden")]
+        );
+        assert!(complete(&format!("delete {prefix}")).is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,17 +1,20 @@
+use crate::filetype::FileType;
 use std::{
     fmt::Display,
     path::{Path, PathBuf},
 };
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct FileInfo {
     pub path: Option<PathBuf>,
+    pub file_type: FileType,
 }
 
 impl FileInfo {
     pub fn from(path: &str) -> Self {
         Self {
             path: Some(PathBuf::from(path)),
+            file_type: FileType::default(),
         }
     }
 
@@ -30,7 +33,7 @@ impl Display for FileInfo {
             .get_path()
             .and_then(|p| p.file_name())
             .and_then(|p| p.to_str())
-            .unwrap();
+            .unwrap_or("[No Name]");
         write!(f, "{name}")
     }
 }
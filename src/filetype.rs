@@ -0,0 +1,231 @@
+use crate::line::Line;
+use std::path::Path;
+
+const MODELINE_LINES: usize = 5;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FileType {
+    #[default]
+    PlainText,
+    Rust,
+    C,
+    Python,
+    JavaScript,
+    Go,
+    Shell,
+    Markdown,
+    Toml,
+    Json,
+    Makefile,
+}
+
+impl FileType {
+    pub const ALL: [Self; 11] = [
+        Self::PlainText,
+        Self::Rust,
+        Self::C,
+        Self::Python,
+        Self::JavaScript,
+        Self::Go,
+        Self::Shell,
+        Self::Markdown,
+        Self::Toml,
+        Self::Json,
+        Self::Makefile,
+    ];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::PlainText => "Text",
+            Self::Rust => "Rust",
+            Self::C => "C",
+            Self::Python => "Python",
+            Self::JavaScript => "JavaScript",
+            Self::Go => "Go",
+            Self::Shell => "Shell",
+            Self::Markdown => "Markdown",
+            Self::Toml => "TOML",
+            Self::Json => "JSON",
+            Self::Makefile => "Makefile",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.trim().to_ascii_lowercase();
+        match name.as_str() {
+            "text" | "txt" | "plain" | "fundamental" => Some(Self::PlainText),
+            "rust" | "rs" => Some(Self::Rust),
+            "c" | "cpp" | "c++" | "h" => Some(Self::C),
+            "python" | "py" => Some(Self::Python),
+            "javascript" | "js" | "typescript" | "ts" | "js2" => Some(Self::JavaScript),
+            "go" | "golang" => Some(Self::Go),
+            "shell" | "sh" | "bash" | "zsh" | "shell-script" => Some(Self::Shell),
+            "markdown" | "md" => Some(Self::Markdown),
+            "toml" | "conf-toml" => Some(Self::Toml),
+            "json" => Some(Self::Json),
+            "make" | "makefile" => Some(Self::Makefile),
+            _ => None,
+        }
+    }
+
+    fn from_path(path: &Path) -> Option<Self> {
+        let file_name = path.file_name()?.to_str()?;
+        if matches!(file_name, "Makefile" | "makefile" | "GNUmakefile") {
+            return Some(Self::Makefile);
+        }
+        match path.extension()?.to_str()? {
+            "rs" => Some(Self::Rust),
+            "c" | "h" | "cc" | "cpp" | "cxx" | "hpp" => Some(Self::C),
+            "py" | "pyw" => Some(Self::Python),
+            "js" | "mjs" | "cjs" | "jsx" | "ts" | "tsx" => Some(Self::JavaScript),
+            "go" => Some(Self::Go),
+            "sh" | "bash" | "zsh" => Some(Self::Shell),
+            "md" | "markdown" => Some(Self::Markdown),
+            "toml" => Some(Self::Toml),
+            "json" => Some(Self::Json),
+            "mk" => Some(Self::Makefile),
+            "txt" => Some(Self::PlainText),
+            _ => None,
+        }
+    }
+
+    fn from_shebang(line: &str) -> Option<Self> {
+        let command = line.strip_prefix("#!")?;
+        let mut words = command.split_whitespace();
+        let mut program = words.next()?.rsplit('/').next()?;
+        // #!/usr/bin/env python3 这种写法要看 env 后面的程序
+        if program == "env" {
+            program = words.find(|word| !word.starts_with('-'))?;
+        }
+        let program = program.trim_end_matches(|ch: char| ch.is_ascii_digit() || ch == '.');
+        match program {
+            "python" => Some(Self::Python),
+            "sh" | "bash" | "zsh" | "dash" | "ksh" => Some(Self::Shell),
+            "node" | "deno" => Some(Self::JavaScript),
+            "make" => Some(Self::Makefile),
+            _ => None,
+        }
+    }
+
+    fn from_modeline(line: &str) -> Option<Self> {
+        Self::from_vim_modeline(line).or_else(|| Self::from_emacs_modeline(line))
+    }
+
+    fn from_vim_modeline(line: &str) -> Option<Self> {
+        let start = ["vim:", "vi:", "ex:"].iter().find_map(|marker| {
+            line.match_indices(marker)
+                .find(|(index, _)| *index == 0 || line[..*index].ends_with(char::is_whitespace))
+                .map(|(index, _)| index.saturating_add(marker.len()))
+        })?;
+        line[start..]
+            .split(|ch: char| ch == ':' || ch.is_whitespace())
+            .find_map(|option| {
+                let (key, value) = option.split_once('=')?;
+                matches!(key, "ft" | "filetype" | "syntax" | "syn")
+                    .then(|| Self::from_name(value))
+                    .flatten()
+            })
+    }
+
+    fn from_emacs_modeline(line: &str) -> Option<Self> {
+        let start = line.find("-*-")?.saturating_add(3);
+        let end = line[start..].find("-*-")?.saturating_add(start);
+        let variables = &line[start..end];
+        if !variables.contains(':') {
+            return Self::from_name(variables.trim());
+        }
+        variables.split(';').find_map(|variable| {
+            let (key, value) = variable.split_once(':')?;
+            key.trim()
+                .eq_ignore_ascii_case("mode")
+                .then(|| Self::from_name(value))
+                .flatten()
+        })
+    }
+
+    pub fn detect(path: Option<&Path>, lines: &[Line]) -> Self {
+        // 优先级: 模式行 > shebang > 扩展名
+        let head = lines.iter().take(MODELINE_LINES);
+        let tail = lines
+            .iter()
+            .skip(MODELINE_LINES.max(lines.len().saturating_sub(MODELINE_LINES)));
+        head.chain(tail)
+            .find_map(|line| Self::from_modeline(&line.to_string()))
+            .or_else(|| Self::from_shebang(&lines.first()?.to_string()))
+            .or_else(|| Self::from_path(path?))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(path: &str, text: &str) -> FileType {
+        let lines: Vec<Line> = text.lines().map(Line::from).collect();
+        FileType::detect((!path.is_empty()).then(|| Path::new(path)), &lines)
+    }
+
+    #[test]
+    fn extension() {
+        assert_eq!(detect("src/main.rs", ""), FileType::Rust);
+        assert_eq!(detect("a.tsx", ""), FileType::JavaScript);
+        assert_eq!(detect("dir/GNUmakefile", ""), FileType::Makefile);
+        assert_eq!(detect("notes.unknown", ""), FileType::PlainText);
+        assert_eq!(detect("", "fn main() {}"), FileType::PlainText);
+    }
+
+    #[test]
+    fn shebang() {
+        assert_eq!(detect("", "#!/bin/sh\necho"), FileType::Shell);
+        assert_eq!(
+            detect("", "#!/usr/bin/env -S python3.11 -u"),
+            FileType::Python
+        );
+        assert_eq!(detect("", "#!/usr/bin/node"), FileType::JavaScript);
+        // 只看第一行
+        assert_eq!(detect("", "\n#!/bin/sh"), FileType::PlainText);
+        // shebang 优先于扩展名, 不认识的程序回退到扩展名
+        assert_eq!(detect("run.txt", "#!/bin/bash"), FileType::Shell);
+        assert_eq!(detect("run.py", "#!/usr/bin/perl"), FileType::Python);
+    }
+
+    #[test]
+    fn modeline() {
+        assert_eq!(detect("", "# vim: set ft=python :"), FileType::Python);
+        assert_eq!(detect("", "/* vi:syntax=c */"), FileType::C);
+        assert_eq!(
+            detect("", "# -*- mode: sh; coding: utf-8 -*-"),
+            FileType::Shell
+        );
+        assert_eq!(detect("", "// -*- rust -*-"), FileType::Rust);
+        // vim: 前面必须是空白或行首
+        assert_eq!(detect("", "novim: ft=go"), FileType::PlainText);
+    }
+
+    #[test]
+    fn modeline_position() {
+        let body = "x\n".repeat(20);
+        assert_eq!(detect("", &format!("{body}# vim: ft=go")), FileType::Go);
+        assert_eq!(
+            detect("", &format!("x\n# vim: ft=go\n{body}")),
+            FileType::Go
+        );
+        // 中间的模式行不算
+        let text = format!("{body}# vim: ft=go\n{body}");
+        assert_eq!(detect("", &text), FileType::PlainText);
+    }
+
+    #[test]
+    fn priority() {
+        // 模式行 > shebang > 扩展名
+        let text = "#!/bin/sh\n# vim: ft=python";
+        assert_eq!(detect("a.rs", text), FileType::Python);
+        assert_eq!(detect("a.rs", "#!/bin/sh"), FileType::Shell);
+        assert_eq!(
+            detect("a.rs", "#!/bin/sh\n# vim: ft=bogus"),
+            FileType::Shell
+        );
+        assert_eq!(detect("a.rs", "# vim: ft=toml"), FileType::Toml);
+    }
+}
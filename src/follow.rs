@@ -0,0 +1,89 @@
+use std::{
+    fs::{File, Metadata},
+    io::{Error, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FollowReset {
+    Truncated,
+    Rotated,
+}
+
+#[derive(Debug, Default)]
+pub struct FollowUpdate {
+    pub reset: Option<FollowReset>,
+    pub lines: Vec<String>,
+    pub partial: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Follower {
+    path: PathBuf,
+    offset: u64,
+    len: u64,
+    file_id: Option<u64>,
+}
+
+impl Follower {
+    pub fn open(path: &Path) -> Result<(Self, FollowUpdate), Error> {
+        let mut follower = Self {
+            path: path.to_path_buf(),
+            offset: 0,
+            len: 0,
+            file_id: None,
+        };
+        let update = follower.poll()?.unwrap_or_default();
+        Ok((follower, update))
+    }
+
+    pub fn poll(&mut self) -> Result<Option<FollowUpdate>, Error> {
+        let mut file = File::open(&self.path)?;
+        let metadata = file.metadata()?;
+        let file_id = Self::file_id(&metadata);
+        let mut update = FollowUpdate::default();
+        if self.file_id.is_some() && file_id != self.file_id {
+            update.reset = Some(FollowReset::Rotated);
+            self.offset = 0;
+        } else if metadata.len() < self.offset {
+            update.reset = Some(FollowReset::Truncated);
+            self.offset = 0;
+        }
+        self.file_id = file_id;
+        if metadata.len() == self.len && update.reset.is_none() {
+            return Ok(None);
+        }
+        self.len = metadata.len();
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        // 只消费完整的行, 不完整的最后一行下次重新读取
+        let complete = bytes
+            .iter()
+            .rposition(|b| *b == b'\n')
+            .map_or(0, |index| index.saturating_add(1));
+        let (complete_bytes, partial_bytes) = bytes.split_at(complete);
+        update.lines = String::from_utf8_lossy(complete_bytes)
+            .lines()
+            .map(String::from)
+            .collect();
+        if !partial_bytes.is_empty() {
+            update.partial = Some(String::from_utf8_lossy(partial_bytes).to_string());
+        }
+        #[allow(clippy::as_conversions)]
+        let complete = complete as u64;
+        self.offset = self.offset.saturating_add(complete);
+        Ok(Some(update))
+    }
+
+    #[cfg(unix)]
+    fn file_id(metadata: &Metadata) -> Option<u64> {
+        use std::os::unix::fs::MetadataExt;
+        Some(metadata.ino())
+    }
+
+    #[cfg(not(unix))]
+    fn file_id(_: &Metadata) -> Option<u64> {
+        None
+    }
+}
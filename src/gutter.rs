@@ -0,0 +1,63 @@
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GutterMode {
+    #[default]
+    Off,
+    Absolute,
+    Relative,
+    Hybrid,
+}
+
+impl GutterMode {
+    pub const fn next(self) -> Self {
+        match self {
+            Self::Off => Self::Absolute,
+            Self::Absolute => Self::Relative,
+            Self::Relative => Self::Hybrid,
+            Self::Hybrid => Self::Off,
+        }
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Absolute => "absolute",
+            Self::Relative => "relative",
+            Self::Hybrid => "hybrid",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "off" => Some(Self::Off),
+            "absolute" => Some(Self::Absolute),
+            "relative" => Some(Self::Relative),
+            "hybrid" => Some(Self::Hybrid),
+            _ => None,
+        }
+    }
+
+    pub const fn is_relative(self) -> bool {
+        matches!(self, Self::Relative | Self::Hybrid)
+    }
+
+    pub fn width(self, total_lines: usize) -> usize {
+        if self == Self::Off {
+            return 0;
+        }
+        let digits = total_lines.max(1).to_string().len();
+        // 数字后面留一个空格
+        digits.saturating_add(1)
+    }
+
+    pub fn label(self, line_index: usize, current_line: usize, width: usize) -> String {
+        let number = match self {
+            Self::Off => return String::new(),
+            Self::Absolute => line_index.saturating_add(1),
+            Self::Relative => line_index.abs_diff(current_line),
+            Self::Hybrid if line_index == current_line => line_index.saturating_add(1),
+            Self::Hybrid => line_index.abs_diff(current_line),
+        };
+        let digits = width.saturating_sub(1);
+        format!("{number:>digits$} ")
+    }
+}
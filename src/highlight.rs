@@ -0,0 +1,695 @@
+use crate::{filetype::FileType, line::Line};
+use std::ops::Range;
+use unicode_segmentation::UnicodeSegmentation;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenType {
+    Keyword,
+    Type,
+    String,
+    Comment,
+    Number,
+    SearchMatch,
+    Selection,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub range: Range<usize>,
+    pub token: TokenType,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LineState {
+    #[default]
+    Normal,
+    BlockComment(usize),
+    String(char),
+    LongString(char),
+    RawString(usize),
+}
+
+#[derive(Debug)]
+pub struct Syntax {
+    file_type: FileType,
+    keywords: &'static [&'static str],
+    types: &'static [&'static str],
+    line_comment: Option<&'static str>,
+    block_comment: Option<(&'static str, &'static str)>,
+    nested_comments: bool,
+    quotes: &'static [char],
+    multiline_quotes: &'static [char],
+    triple_quotes: bool,
+    raw_strings: bool,
+    lifetimes: bool,
+}
+
+const SYNTAXES: &[Syntax] = &[
+    Syntax {
+        file_type: FileType::Rust,
+        keywords: &[
+            "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+            "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod",
+            "move", "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super",
+            "trait", "true", "type", "unsafe", "use", "where", "while",
+        ],
+        types: &[
+            "bool", "char", "str", "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32",
+            "i64", "i128", "isize", "f32", "f64", "String", "Vec", "Option", "Result", "Box",
+            "Some", "None", "Ok", "Err",
+        ],
+        line_comment: Some("//"),
+        block_comment: Some(("/*", "*/")),
+        nested_comments: true,
+        quotes: &['"', '\''],
+        multiline_quotes: &['"'],
+        triple_quotes: false,
+        raw_strings: true,
+        lifetimes: true,
+    },
+    Syntax {
+        file_type: FileType::C,
+        keywords: &[
+            "break",
+            "case",
+            "class",
+            "const",
+            "continue",
+            "default",
+            "do",
+            "else",
+            "enum",
+            "extern",
+            "for",
+            "goto",
+            "if",
+            "namespace",
+            "nullptr",
+            "private",
+            "protected",
+            "public",
+            "return",
+            "sizeof",
+            "static",
+            "struct",
+            "switch",
+            "template",
+            "typedef",
+            "union",
+            "using",
+            "virtual",
+            "volatile",
+            "while",
+            "#include",
+            "#define",
+            "#ifdef",
+            "#ifndef",
+            "#endif",
+            "#if",
+            "#else",
+            "#pragma",
+        ],
+        types: &[
+            "auto", "bool", "char", "double", "float", "int", "long", "short", "signed",
+            "unsigned", "void", "size_t",
+        ],
+        line_comment: Some("//"),
+        block_comment: Some(("/*", "*/")),
+        nested_comments: false,
+        quotes: &['"', '\''],
+        multiline_quotes: &[],
+        triple_quotes: false,
+        raw_strings: false,
+        lifetimes: false,
+    },
+    Syntax {
+        file_type: FileType::Python,
+        keywords: &[
+            "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+            "elif", "else", "except", "False", "finally", "for", "from", "global", "if", "import",
+            "in", "is", "lambda", "None", "nonlocal", "not", "or", "pass", "raise", "return",
+            "True", "try", "while", "with", "yield",
+        ],
+        types: &[
+            "bool", "bytes", "dict", "float", "int", "list", "object", "set", "str", "tuple",
+        ],
+        line_comment: Some("#"),
+        block_comment: None,
+        nested_comments: false,
+        quotes: &['"', '\''],
+        multiline_quotes: &[],
+        triple_quotes: true,
+        raw_strings: false,
+        lifetimes: false,
+    },
+    Syntax {
+        file_type: FileType::JavaScript,
+        keywords: &[
+            "async",
+            "await",
+            "break",
+            "case",
+            "catch",
+            "class",
+            "const",
+            "continue",
+            "default",
+            "delete",
+            "do",
+            "else",
+            "export",
+            "extends",
+            "false",
+            "finally",
+            "for",
+            "function",
+            "if",
+            "import",
+            "in",
+            "instanceof",
+            "interface",
+            "let",
+            "new",
+            "null",
+            "return",
+            "switch",
+            "this",
+            "throw",
+            "true",
+            "try",
+            "type",
+            "typeof",
+            "undefined",
+            "var",
+            "while",
+            "yield",
+        ],
+        types: &[
+            "any", "boolean", "number", "string", "void", "never", "unknown", "Array", "Promise",
+        ],
+        line_comment: Some("//"),
+        block_comment: Some(("/*", "*/")),
+        nested_comments: false,
+        quotes: &['"', '\'', '`'],
+        multiline_quotes: &['`'],
+        triple_quotes: false,
+        raw_strings: false,
+        lifetimes: false,
+    },
+    Syntax {
+        file_type: FileType::Go,
+        keywords: &[
+            "break",
+            "case",
+            "chan",
+            "const",
+            "continue",
+            "default",
+            "defer",
+            "else",
+            "false",
+            "for",
+            "func",
+            "go",
+            "goto",
+            "if",
+            "import",
+            "interface",
+            "map",
+            "nil",
+            "package",
+            "range",
+            "return",
+            "select",
+            "struct",
+            "switch",
+            "true",
+            "type",
+            "var",
+        ],
+        types: &[
+            "bool", "byte", "error", "float32", "float64", "int", "int8", "int16", "int32",
+            "int64", "rune", "string", "uint", "uint8", "uint16", "uint32", "uint64",
+        ],
+        line_comment: Some("//"),
+        block_comment: Some(("/*", "*/")),
+        nested_comments: false,
+        quotes: &['"', '\'', '`'],
+        multiline_quotes: &['`'],
+        triple_quotes: false,
+        raw_strings: false,
+        lifetimes: false,
+    },
+    Syntax {
+        file_type: FileType::Shell,
+        keywords: &[
+            "case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "function", "if",
+            "in", "local", "return", "then", "until", "while",
+        ],
+        types: &[],
+        line_comment: Some("#"),
+        block_comment: None,
+        nested_comments: false,
+        quotes: &['"', '\''],
+        multiline_quotes: &['"', '\''],
+        triple_quotes: false,
+        raw_strings: false,
+        lifetimes: false,
+    },
+    Syntax {
+        file_type: FileType::Toml,
+        keywords: &[],
+        types: &[],
+        line_comment: Some("#"),
+        block_comment: None,
+        nested_comments: false,
+        quotes: &['"', '\''],
+        multiline_quotes: &[],
+        triple_quotes: true,
+        raw_strings: false,
+        lifetimes: false,
+    },
+    Syntax {
+        file_type: FileType::Json,
+        keywords: &["false", "null", "true"],
+        types: &[],
+        line_comment: None,
+        block_comment: None,
+        nested_comments: false,
+        quotes: &['"'],
+        multiline_quotes: &[],
+        triple_quotes: false,
+        raw_strings: false,
+        lifetimes: false,
+    },
+    Syntax {
+        file_type: FileType::Makefile,
+        keywords: &[
+            "define", "else", "endef", "endif", "export", "ifdef", "ifeq", "ifndef", "ifneq",
+            "include", "override",
+        ],
+        types: &[],
+        line_comment: Some("#"),
+        block_comment: None,
+        nested_comments: false,
+        quotes: &['"', '\''],
+        multiline_quotes: &[],
+        triple_quotes: false,
+        raw_strings: false,
+        lifetimes: false,
+    },
+];
+
+impl Syntax {
+    pub fn for_file_type(file_type: FileType) -> Option<&'static Self> {
+        SYNTAXES.iter().find(|syntax| syntax.file_type == file_type)
+    }
+
+    fn is_word(grapheme: &str) -> bool {
+        grapheme
+            .chars()
+            .all(|ch| ch.is_alphanumeric() || ch == '_' || ch == '#')
+    }
+
+    pub fn highlight(&self, text: &str, state: LineState) -> (Vec<Span>, LineState) {
+        let graphemes: Vec<(usize, &str)> = text.grapheme_indices(true).collect();
+        let len = graphemes.len();
+        let rest = |index: usize| graphemes.get(index).map_or("", |(byte, _)| &text[*byte..]);
+        let grapheme = |index: usize| graphemes.get(index).map_or("", |(_, g)| *g);
+        let mut spans = Vec::new();
+        let mut push = |range: Range<usize>, token: TokenType| {
+            if !range.is_empty() {
+                spans.push(Span { range, token });
+            }
+        };
+        let mut index = 0;
+        // 先处理从上一行延续下来的注释或字符串
+        let mut state = state;
+        if state != LineState::Normal {
+            let (end, next) = self.scan_continued(text, &graphemes, 0, state);
+            let token = if matches!(state, LineState::BlockComment(_)) {
+                TokenType::Comment
+            } else {
+                TokenType::String
+            };
+            push(0..end, token);
+            index = end;
+            state = next;
+        }
+        while index < len && state == LineState::Normal {
+            let current = rest(index);
+            let first = current.chars().next().unwrap_or_default();
+            if self
+                .line_comment
+                .is_some_and(|marker| current.starts_with(marker))
+            {
+                push(index..len, TokenType::Comment);
+                break;
+            }
+            if let Some((open, _)) = self
+                .block_comment
+                .filter(|(open, _)| current.starts_with(open))
+            {
+                let start = index;
+                let after = index.saturating_add(open.chars().count());
+                let (end, next) =
+                    self.scan_continued(text, &graphemes, after, LineState::BlockComment(1));
+                push(start..end, TokenType::Comment);
+                index = end;
+                state = next;
+                continue;
+            }
+            if self.triple_quotes && (current.starts_with("\"\"\"") || current.starts_with("'''")) {
+                let start = index;
+                let (end, next) = self.scan_continued(
+                    text,
+                    &graphemes,
+                    index.saturating_add(3),
+                    LineState::LongString(first),
+                );
+                push(start..end, TokenType::String);
+                index = end;
+                state = next;
+                continue;
+            }
+            let previous_is_word = index > 0 && Self::is_word(grapheme(index.saturating_sub(1)));
+            if self.raw_strings && first == 'r' && !previous_is_word {
+                let hashes = current.chars().skip(1).take_while(|ch| *ch == '#').count();
+                if current.chars().nth(hashes.saturating_add(1)) == Some('"') {
+                    let start = index;
+                    let (end, next) = self.scan_continued(
+                        text,
+                        &graphemes,
+                        index.saturating_add(hashes).saturating_add(2),
+                        LineState::RawString(hashes),
+                    );
+                    push(start..end, TokenType::String);
+                    index = end;
+                    state = next;
+                    continue;
+                }
+            }
+            if self.quotes.contains(&first) {
+                let start = index;
+                if self.lifetimes && first == '\'' {
+                    // 区分字符字面量和生命周期
+                    let end = if grapheme(index.saturating_add(1)) == "\\" {
+                        (index.saturating_add(2)..len.min(index.saturating_add(12)))
+                            .find(|at| grapheme(*at) == "'")
+                            .map(|at| at.saturating_add(1))
+                    } else if grapheme(index.saturating_add(2)) == "'" {
+                        Some(index.saturating_add(3))
+                    } else {
+                        None
+                    };
+                    match end {
+                        Some(end) => {
+                            push(start..end, TokenType::String);
+                            index = end;
+                        }
+                        None => index = index.saturating_add(1),
+                    }
+                    continue;
+                }
+                let (end, next) = self.scan_continued(
+                    text,
+                    &graphemes,
+                    index.saturating_add(1),
+                    LineState::String(first),
+                );
+                push(start..end, TokenType::String);
+                index = end;
+                state = if self.multiline_quotes.contains(&first) {
+                    next
+                } else {
+                    LineState::Normal
+                };
+                continue;
+            }
+            if Self::is_word(grapheme(index)) && !previous_is_word {
+                let end = (index..len)
+                    .find(|at| !Self::is_word(grapheme(*at)))
+                    .unwrap_or(len);
+                let word: String = (index..end).map(grapheme).collect();
+                if first.is_ascii_digit() {
+                    // 小数点也算在数字里
+                    let end = (index..len)
+                        .find(|at| !Self::is_word(grapheme(*at)) && grapheme(*at) != ".")
+                        .unwrap_or(len);
+                    push(index..end, TokenType::Number);
+                    index = end;
+                    continue;
+                }
+                if self.keywords.contains(&word.as_str()) {
+                    push(index..end, TokenType::Keyword);
+                } else if self.types.contains(&word.as_str()) {
+                    push(index..end, TokenType::Type);
+                }
+                index = end;
+                continue;
+            }
+            index = index.saturating_add(1);
+        }
+        (spans, state)
+    }
+
+    fn scan_continued(
+        &self,
+        text: &str,
+        graphemes: &[(usize, &str)],
+        from: usize,
+        state: LineState,
+    ) -> (usize, LineState) {
+        let len = graphemes.len();
+        let rest = |index: usize| graphemes.get(index).map_or("", |(byte, _)| &text[*byte..]);
+        let mut index = from;
+        let mut depth = match state {
+            LineState::BlockComment(depth) => depth,
+            _ => 0,
+        };
+        while index < len {
+            let current = rest(index);
+            match state {
+                LineState::BlockComment(_) => {
+                    let (open, close) = self.block_comment.unwrap_or(("/*", "*/"));
+                    if current.starts_with(close) {
+                        index = index.saturating_add(close.chars().count());
+                        depth = depth.saturating_sub(1);
+                        if depth == 0 {
+                            return (index, LineState::Normal);
+                        }
+                        continue;
+                    }
+                    if self.nested_comments && current.starts_with(open) {
+                        index = index.saturating_add(open.chars().count());
+                        depth = depth.saturating_add(1);
+                        continue;
+                    }
+                }
+                LineState::String(quote) => {
+                    if current.starts_with('\\') {
+                        index = index.saturating_add(2);
+                        continue;
+                    }
+                    if current.starts_with(quote) {
+                        return (index.saturating_add(1), LineState::Normal);
+                    }
+                }
+                LineState::LongString(quote) => {
+                    let close = quote.to_string().repeat(3);
+                    if current.starts_with('\\') {
+                        index = index.saturating_add(2);
+                        continue;
+                    }
+                    if current.starts_with(&close) {
+                        return (index.saturating_add(3), LineState::Normal);
+                    }
+                }
+                LineState::RawString(hashes) => {
+                    let close = format!("\"{}", "#".repeat(hashes));
+                    if current.starts_with(&close) {
+                        return (index.saturating_add(close.len()), LineState::Normal);
+                    }
+                }
+                LineState::Normal => return (index, state),
+            }
+            index = index.saturating_add(1);
+        }
+        let state = match state {
+            LineState::BlockComment(_) => LineState::BlockComment(depth),
+            state => state,
+        };
+        (len, state)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Highlighter {
+    syntax: Option<&'static Syntax>,
+    lines: Vec<(Vec<Span>, LineState)>,
+}
+
+impl Highlighter {
+    pub fn for_file_type(file_type: FileType) -> Self {
+        Self {
+            syntax: Syntax::for_file_type(file_type),
+            lines: Vec::new(),
+        }
+    }
+
+    pub fn invalidate(&mut self, line_index: usize) {
+        self.lines.truncate(line_index);
+    }
+
+    pub fn update(&mut self, lines: &[Line], until: usize) {
+        let Some(syntax) = self.syntax else {
+            return;
+        };
+        // 只重新高亮从第一处修改到可见区域末尾的行
+        let end = until.min(lines.len());
+        for line in lines.iter().take(end).skip(self.lines.len()) {
+            let state = self
+                .lines
+                .last()
+                .map_or(LineState::Normal, |(_, state)| *state);
+            self.lines.push(syntax.highlight(&line.to_string(), state));
+        }
+    }
+
+    pub fn spans(&self, line_index: usize) -> &[Span] {
+        self.lines
+            .get(line_index)
+            .map_or(&[], |(spans, _)| spans.as_slice())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 逐行高亮, 把上一行的状态传给下一行
+    fn highlight(file_type: FileType, lines: &[&str]) -> Vec<(Vec<Span>, LineState)> {
+        let syntax = Syntax::for_file_type(file_type).unwrap();
+        let mut state = LineState::Normal;
+        lines
+            .iter()
+            .map(|line| {
+                let (spans, next) = syntax.highlight(line, state);
+                state = next;
+                (spans, next)
+            })
+            .collect()
+    }
+
+    fn span(range: Range<usize>, token: TokenType) -> Span {
+        Span { range, token }
+    }
+
+    #[test]
+    fn block_comment_spans_lines() {
+        let lines = highlight(FileType::C, &["int a; /* one", "two", "three */ int b;"]);
+        assert_eq!(
+            lines[0].0,
+            vec![span(0..3, TokenType::Type), span(7..13, TokenType::Comment)]
+        );
+        assert_eq!(lines[0].1, LineState::BlockComment(1));
+        assert_eq!(lines[1].0, vec![span(0..3, TokenType::Comment)]);
+        assert_eq!(
+            lines[2].0,
+            vec![span(0..8, TokenType::Comment), span(9..12, TokenType::Type)]
+        );
+        assert_eq!(lines[2].1, LineState::Normal);
+    }
+
+    #[test]
+    fn nested_comments() {
+        let lines = highlight(FileType::Rust, &["/* a /* b */", "c */ fn"]);
+        assert_eq!(lines[0].1, LineState::BlockComment(1));
+        assert_eq!(
+            lines[1].0,
+            vec![
+                span(0..4, TokenType::Comment),
+                span(5..7, TokenType::Keyword)
+            ]
+        );
+        // C 的注释不嵌套
+        let lines = highlight(FileType::C, &["/* a /* b */ int"]);
+        assert_eq!(lines[0].1, LineState::Normal);
+        assert_eq!(lines[0].0[1], span(13..16, TokenType::Type));
+    }
+
+    #[test]
+    fn multiline_strings() {
+        let lines = highlight(
+            FileType::Rust,
+            &["let s = \"one", "two \\\" x", "end\"; fn"],
+        );
+        assert_eq!(lines[0].1, LineState::String('"'));
+        assert_eq!(lines[1].0, vec![span(0..8, TokenType::String)]);
+        assert_eq!(lines[1].1, LineState::String('"'));
+        assert_eq!(
+            lines[2].0,
+            vec![
+                span(0..4, TokenType::String),
+                span(6..8, TokenType::Keyword)
+            ]
+        );
+        // 不支持跨行的引号在行尾结束
+        let lines = highlight(FileType::C, &["\"open", "int"]);
+        assert_eq!(lines[0].1, LineState::Normal);
+        assert_eq!(lines[1].0, vec![span(0..3, TokenType::Type)]);
+    }
+
+    #[test]
+    fn long_and_raw_strings() {
+        let lines = highlight(FileType::Python, &["x = '''a", "b", "c''' if"]);
+        assert_eq!(lines[0].1, LineState::LongString('\''));
+        assert_eq!(lines[1].0, vec![span(0..1, TokenType::String)]);
+        assert_eq!(
+            lines[2].0,
+            vec![
+                span(0..4, TokenType::String),
+                span(5..7, TokenType::Keyword)
+            ]
+        );
+        let lines = highlight(FileType::Rust, &["r#\"a \" b", "c\"# fn"]);
+        assert_eq!(lines[0].1, LineState::RawString(1));
+        assert_eq!(
+            lines[1].0,
+            vec![
+                span(0..3, TokenType::String),
+                span(4..6, TokenType::Keyword)
+            ]
+        );
+    }
+
+    #[test]
+    fn comment_markers_inside_strings() {
+        let lines = highlight(FileType::Rust, &["\"// /*\" fn", "'a' 'b"]);
+        assert_eq!(lines[0].1, LineState::Normal);
+        assert_eq!(
+            lines[0].0,
+            vec![
+                span(0..7, TokenType::String),
+                span(8..10, TokenType::Keyword)
+            ]
+        );
+        // 生命周期不是字符字面量
+        assert_eq!(lines[1].0, vec![span(0..3, TokenType::String)]);
+    }
+
+    #[test]
+    fn highlighter_resumes_after_invalidate() {
+        let lines: Vec<Line> = ["/* a", "b */", "fn"]
+            .iter()
+            .map(|text| Line::from(text))
+            .collect();
+        let mut highlighter = Highlighter::for_file_type(FileType::Rust);
+        highlighter.update(&lines, 3);
+        assert_eq!(highlighter.spans(2), [span(0..2, TokenType::Keyword)]);
+        highlighter.invalidate(1);
+        highlighter.update(&lines, 3);
+        assert_eq!(highlighter.spans(1), [span(0..4, TokenType::Comment)]);
+        assert_eq!(highlighter.spans(2), [span(0..2, TokenType::Keyword)]);
+    }
+}
@@ -0,0 +1,210 @@
+use crate::{filetype::FileType, line::Line};
+
+const DEFAULT_INDENT_WIDTH: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndentStyle {
+    Tabs,
+    Spaces,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndentSettings {
+    pub style: IndentStyle,
+    pub width: usize,
+}
+
+impl Default for IndentSettings {
+    fn default() -> Self {
+        Self {
+            style: IndentStyle::Spaces,
+            width: DEFAULT_INDENT_WIDTH,
+        }
+    }
+}
+
+impl IndentSettings {
+    pub fn for_file_type(file_type: FileType) -> Self {
+        match file_type {
+            FileType::Go | FileType::Makefile => Self {
+                style: IndentStyle::Tabs,
+                ..Self::default()
+            },
+            FileType::JavaScript | FileType::Json => Self {
+                style: IndentStyle::Spaces,
+                width: 2,
+            },
+            _ => Self::default(),
+        }
+    }
+
+    pub fn detect(lines: &[Line]) -> Option<Self> {
+        let mut tab_lines = 0;
+        let mut space_lines = 0;
+        let mut deltas = [0usize; 9];
+        let mut previous_spaces = 0;
+        for line in lines {
+            let text = line.to_string();
+            if text.trim().is_empty() {
+                continue;
+            }
+            if text.starts_with('\t') {
+                tab_lines += 1;
+                continue;
+            }
+            let spaces = text
+                .len()
+                .saturating_sub(text.trim_start_matches(' ').len());
+            if spaces > 0 {
+                space_lines += 1;
+            }
+            // 统计相邻行缩进的增量, 出现最多的就是缩进宽度
+            let delta = spaces.saturating_sub(previous_spaces);
+            if let Some(count) = deltas.get_mut(delta).filter(|_| delta >= 2) {
+                *count += 1;
+            }
+            previous_spaces = spaces;
+        }
+        if tab_lines == 0 && space_lines == 0 {
+            return None;
+        }
+        if tab_lines > space_lines {
+            return Some(Self {
+                style: IndentStyle::Tabs,
+                ..Self::default()
+            });
+        }
+        let width = deltas
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .max_by_key(|(width, count)| (**count, *width))
+            .map_or(DEFAULT_INDENT_WIDTH, |(width, _)| width);
+        Some(Self {
+            style: IndentStyle::Spaces,
+            width,
+        })
+    }
+
+    pub fn unit(&self) -> String {
+        match self.style {
+            IndentStyle::Tabs => String::from("\t"),
+            IndentStyle::Spaces => " ".repeat(self.width.max(1)),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self.style {
+            IndentStyle::Tabs => String::from("Tabs"),
+            IndentStyle::Spaces => format!("Spaces: {}", self.width),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct IndentRules {
+    pub openers: &'static [char],
+    pub closers: &'static [char],
+}
+
+impl Default for IndentRules {
+    fn default() -> Self {
+        Self {
+            openers: &['{', '(', '['],
+            closers: &['}', ')', ']'],
+        }
+    }
+}
+
+impl IndentRules {
+    pub fn for_file_type(file_type: FileType) -> Self {
+        match file_type {
+            FileType::Python => Self {
+                openers: &[':', '{', '(', '['],
+                ..Self::default()
+            },
+            _ => Self::default(),
+        }
+    }
+
+    pub fn closer_for(opener: char) -> Option<char> {
+        match opener {
+            '{' => Some('}'),
+            '(' => Some(')'),
+            '[' => Some(']'),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(text: &str) -> Option<IndentSettings> {
+        let lines: Vec<Line> = text.lines().map(Line::from).collect();
+        IndentSettings::detect(&lines)
+    }
+
+    fn spaces(width: usize) -> Option<IndentSettings> {
+        Some(IndentSettings {
+            style: IndentStyle::Spaces,
+            width,
+        })
+    }
+
+    #[test]
+    fn no_indentation() {
+        assert_eq!(detect(""), None);
+        assert_eq!(detect("a\nb\n\n   \nc"), None);
+    }
+
+    #[test]
+    fn tabs() {
+        let detected = detect("fn a() {\n\tb();\n\tif c {\n\t\td();\n\t}\n}");
+        assert_eq!(
+            detected.map(|settings| settings.style),
+            Some(IndentStyle::Tabs)
+        );
+        // 空格行多于 Tab 行时按空格算
+        let detected = detect("a\n\tb\n  c\n  d");
+        assert_eq!(
+            detected.map(|settings| settings.style),
+            Some(IndentStyle::Spaces)
+        );
+    }
+
+    #[test]
+    fn space_width() {
+        assert_eq!(detect("a {\n  b {\n    c\n  }\n}"), spaces(2));
+        assert_eq!(detect("a {\n    b {\n        c\n    }\n}"), spaces(4));
+        assert_eq!(detect("def a():\n        b\n        c"), spaces(8));
+        // 续行的对齐空格比缩进少见
+        let text = "a(\n  b,\n  c)\nd {\n    e {\n        f\n    }\n    g {\n        h\n    }\n}";
+        assert_eq!(detect(text), spaces(4));
+    }
+
+    #[test]
+    fn odd_deltas() {
+        // 只差一个空格的行不能决定宽度, 回退到默认值
+        assert_eq!(detect("a\n b\n  c"), spaces(DEFAULT_INDENT_WIDTH));
+        // 超过 8 的增量忽略
+        assert_eq!(detect("a\n            b"), spaces(DEFAULT_INDENT_WIDTH));
+    }
+
+    #[test]
+    fn unit_and_name() {
+        let tabs = IndentSettings {
+            style: IndentStyle::Tabs,
+            width: 4,
+        };
+        assert_eq!(tabs.unit(), "\t");
+        assert_eq!(tabs.name(), "Tabs");
+        let two = IndentSettings {
+            style: IndentStyle::Spaces,
+            width: 2,
+        };
+        assert_eq!(two.unit(), "  ");
+        assert_eq!(two.name(), "Spaces: 2");
+    }
+}
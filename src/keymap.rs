@@ -0,0 +1,476 @@
+use crate::command::Command;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::fmt::{self, Display};
+
+const DEFAULT_BINDINGS: &[(&str, &str)] = &[
+    ("Up", "up"),
+    ("Down", "down"),
+    ("Left", "left"),
+    ("Right", "right"),
+    ("PageUp", "page_up"),
+    ("PageDown", "page_down"),
+    ("Home", "home"),
+    ("End", "end"),
+    ("Ctrl-Left", "word_left"),
+    ("Ctrl-Right", "word_right"),
+    ("Ctrl-Up", "paragraph_up"),
+    ("Ctrl-Down", "paragraph_down"),
+    ("Ctrl-Home", "start_of_document"),
+    ("Ctrl-End", "end_of_document"),
+    ("Alt-Up", "parent_node"),
+    ("Alt-Right", "next_sibling"),
+    ("Alt-Left", "previous_sibling"),
+    ("Tab", "insert_tab"),
+    ("Enter", "newline"),
+    ("Delete", "delete"),
+    ("Backspace", "delete_backward"),
+    ("Ctrl-Backspace", "delete_word_backward"),
+    ("Alt-Backspace", "delete_word_backward"),
+    // 多数终端把 Ctrl+Backspace 发送为 ^H
+    ("Ctrl-H", "delete_word_backward"),
+    ("Ctrl-Delete", "delete_word_forward"),
+    ("Alt-d", "delete_word_forward"),
+    ("Ctrl-K", "kill_to_end_of_line"),
+    ("Ctrl-U", "kill_to_start_of_line"),
+    ("Ctrl-D", "delete_line"),
+    ("Ctrl-S", "save"),
+    ("Ctrl-Q", "quit"),
+    ("Ctrl-R", "open_recent"),
+    ("Ctrl-N", "next_buffer"),
+    ("Ctrl-B", "previous_buffer"),
+    ("Ctrl-W", "save_session"),
+    ("Ctrl-O", "open_session"),
+    ("Ctrl-T", "toggle_readonly"),
+    ("Ctrl-F", "follow"),
+    ("Ctrl-L", "toggle_gutter"),
+    ("Alt-z", "toggle_wrap"),
+    ("Ctrl-Y", "yank"),
+    ("Alt-y", "yank_pop"),
+    ("Alt-t", "set_file_type"),
+    ("Alt-r", "reload_config"),
+    ("Ctrl-P", "command_palette"),
+    ("Alt-;", "command_line"),
+    ("Esc", "dismiss"),
+];
+
+// 在默认绑定之上覆盖
+const EMACS_BINDINGS: &[(&str, &str)] = &[
+    ("Ctrl-A", "home"),
+    ("Ctrl-E", "end"),
+    ("Ctrl-F", "right"),
+    ("Ctrl-B", "left"),
+    ("Ctrl-N", "down"),
+    ("Ctrl-P", "up"),
+    ("Alt-f", "word_right"),
+    ("Alt-b", "word_left"),
+    ("Ctrl-V", "page_down"),
+    ("Alt-v", "page_up"),
+    ("Alt-{", "paragraph_up"),
+    ("Alt-}", "paragraph_down"),
+    ("Alt-<", "start_of_document"),
+    ("Alt->", "end_of_document"),
+    ("Ctrl-D", "delete"),
+    ("Ctrl-K", "kill_to_end_of_line"),
+    ("Ctrl-Y", "yank"),
+    ("Alt-y", "yank_pop"),
+    ("Ctrl-Space", "set_mark"),
+    ("Ctrl-W", "kill_region"),
+    ("Alt-w", "copy_region"),
+    ("Ctrl-G", "dismiss"),
+    ("Alt-x", "command_palette"),
+    // Emacs 里 Ctrl-Q 是转义输入, 避免误退出
+    ("Ctrl-Q", "none"),
+    ("Ctrl-X Ctrl-S", "save"),
+    ("Ctrl-X Ctrl-C", "quit"),
+    ("Ctrl-X Ctrl-Q", "toggle_readonly"),
+    ("Ctrl-X Ctrl-R", "open_recent"),
+    ("Ctrl-X Right", "next_buffer"),
+    ("Ctrl-X Left", "previous_buffer"),
+    ("Ctrl-X r", "open_session"),
+    ("Ctrl-X s", "save_session"),
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Preset {
+    #[default]
+    Default,
+    Vi,
+    Emacs,
+}
+
+impl Preset {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "default" => Some(Self::Default),
+            "vi" | "vim" => Some(Self::Vi),
+            "emacs" => Some(Self::Emacs),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl From<KeyEvent> for Key {
+    fn from(event: KeyEvent) -> Self {
+        let mut modifiers = event.modifiers;
+        // 字符本身已经区分大小写, 忽略 Shift
+        if matches!(event.code, KeyCode::Char(_)) {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        Self {
+            code: event.code,
+            modifiers,
+        }
+    }
+}
+
+impl Key {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = text;
+        while let Some((modifier, key)) = rest.split_once('-').filter(|(_, key)| !key.is_empty()) {
+            modifiers |= match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "c" => KeyModifiers::CONTROL,
+                "alt" | "m" => KeyModifiers::ALT,
+                "shift" | "s" => KeyModifiers::SHIFT,
+                _ => return Err(format!("unknown modifier '{modifier}' in '{text}'")),
+            };
+            rest = key;
+        }
+        let code = match rest.to_ascii_lowercase().as_str() {
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "enter" => KeyCode::Enter,
+            "esc" => KeyCode::Esc,
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" => KeyCode::Insert,
+            "space" => KeyCode::Char(' '),
+            name if name.len() > 1 && name.starts_with('f') => name[1..]
+                .parse()
+                .map(KeyCode::F)
+                .map_err(|_| format!("unknown key '{rest}'"))?,
+            _ => {
+                let mut chars = rest.chars();
+                match (chars.next(), chars.next()) {
+                    // Ctrl 组合键在终端里不区分大小写, Alt 组合键区分
+                    (Some(ch), None) if modifiers.contains(KeyModifiers::CONTROL) => {
+                        KeyCode::Char(ch.to_ascii_lowercase())
+                    }
+                    // 终端把 Shift 体现在字符本身的大小写上
+                    (Some(ch), None) if modifiers.contains(KeyModifiers::SHIFT) => {
+                        KeyCode::Char(ch.to_ascii_uppercase())
+                    }
+                    (Some(ch), None) => KeyCode::Char(ch),
+                    _ => return Err(format!("unknown key '{rest}'")),
+                }
+            }
+        };
+        Ok(Self::from(KeyEvent::new(code, modifiers)))
+    }
+
+    pub fn parse_sequence(text: &str) -> Result<Vec<Self>, String> {
+        let keys = text
+            .split_whitespace()
+            .map(Self::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        if keys.is_empty() {
+            return Err(String::from("empty key sequence"));
+        }
+        Ok(keys)
+    }
+
+    pub fn is_escape(self) -> bool {
+        self.code == KeyCode::Esc
+    }
+
+    pub fn is_enter(self) -> bool {
+        self == Self::from(KeyEvent::from(KeyCode::Enter))
+    }
+
+    pub fn is_tab(self) -> bool {
+        self == Self::from(KeyEvent::from(KeyCode::Tab))
+    }
+
+    pub fn is_backspace(self) -> bool {
+        self == Self::from(KeyEvent::from(KeyCode::Backspace))
+    }
+
+    pub fn as_char(self) -> Option<char> {
+        match (self.code, self.modifiers) {
+            (KeyCode::Char(ch), KeyModifiers::NONE) => Some(ch),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in [
+            (KeyModifiers::CONTROL, "Ctrl-"),
+            (KeyModifiers::ALT, "Alt-"),
+            (KeyModifiers::SHIFT, "Shift-"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{name}")?;
+            }
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(ch) if self.modifiers.contains(KeyModifiers::CONTROL) => {
+                write!(f, "{}", ch.to_ascii_uppercase())
+            }
+            KeyCode::Char(ch) => write!(f, "{ch}"),
+            KeyCode::F(number) => write!(f, "F{number}"),
+            code => write!(f, "{code:?}"),
+        }
+    }
+}
+
+pub enum Lookup {
+    Command(Command),
+    Pending,
+    Unbound,
+}
+
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<(Vec<Key>, Command)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::new(Preset::Default)
+    }
+}
+
+impl Keymap {
+    pub fn new(preset: Preset) -> Self {
+        let mut keymap = Self {
+            bindings: Vec::new(),
+        };
+        let overrides = match preset {
+            Preset::Default | Preset::Vi => &[],
+            Preset::Emacs => EMACS_BINDINGS,
+        };
+        for (keys, name) in DEFAULT_BINDINGS.iter().chain(overrides) {
+            let _ = keymap.bind(keys, name);
+        }
+        keymap
+    }
+
+    pub fn bind(&mut self, keys: &str, name: &str) -> Result<(), String> {
+        let keys = Key::parse_sequence(keys)?;
+        let command = match name {
+            "none" => None,
+            name => {
+                Some(Command::from_name(name).ok_or_else(|| format!("unknown command '{name}'"))?)
+            }
+        };
+        // 新绑定与前缀冲突的旧绑定都会被移除
+        self.bindings
+            .retain(|(bound, _)| !bound.starts_with(&keys) && !keys.starts_with(bound));
+        if let Some(command) = command {
+            self.bindings.push((keys, command));
+        }
+        Ok(())
+    }
+
+    pub fn keys_for(&self, command: Command) -> Vec<String> {
+        self.bindings
+            .iter()
+            .filter(|(_, bound)| *bound == command)
+            .map(|(keys, _)| sequence_to_string(keys))
+            .collect()
+    }
+
+    // 最后绑定的组合键优先, 通常来自预设或用户配置
+    pub fn key_for(&self, command: Command) -> Option<String> {
+        self.keys_for(command).pop()
+    }
+
+    pub fn lookup(&self, keys: &[Key]) -> Lookup {
+        let mut pending = false;
+        for (bound, command) in &self.bindings {
+            if bound == keys {
+                return Lookup::Command(*command);
+            }
+            pending |= bound.starts_with(keys);
+        }
+        if pending {
+            Lookup::Pending
+        } else {
+            Lookup::Unbound
+        }
+    }
+}
+
+pub fn sequence_to_string(keys: &[Key]) -> String {
+    keys.iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::{Edit, Move, System};
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> Key {
+        Key::from(KeyEvent::new(code, modifiers))
+    }
+
+    fn command(keymap: &Keymap, keys: &str) -> Option<Command> {
+        match keymap.lookup(&Key::parse_sequence(keys).unwrap()) {
+            Lookup::Command(command) => Some(command),
+            Lookup::Pending | Lookup::Unbound => None,
+        }
+    }
+
+    #[test]
+    fn parse_keys() {
+        assert_eq!(
+            Key::parse("Ctrl-S"),
+            Ok(key(KeyCode::Char('s'), KeyModifiers::CONTROL))
+        );
+        assert_eq!(
+            Key::parse("c-s"),
+            Ok(key(KeyCode::Char('s'), KeyModifiers::CONTROL))
+        );
+        assert_eq!(
+            Key::parse("Alt-f"),
+            Ok(key(KeyCode::Char('f'), KeyModifiers::ALT))
+        );
+        assert_eq!(
+            Key::parse("Alt-F"),
+            Ok(key(KeyCode::Char('F'), KeyModifiers::ALT))
+        );
+        assert_eq!(
+            Key::parse("Alt-Shift-f"),
+            Ok(key(KeyCode::Char('F'), KeyModifiers::ALT))
+        );
+        assert_eq!(
+            Key::parse("-"),
+            Ok(key(KeyCode::Char('-'), KeyModifiers::NONE))
+        );
+        assert_eq!(
+            Key::parse("Alt--"),
+            Ok(key(KeyCode::Char('-'), KeyModifiers::ALT))
+        );
+        assert_eq!(
+            Key::parse("Ctrl-Space"),
+            Ok(key(KeyCode::Char(' '), KeyModifiers::CONTROL))
+        );
+        assert_eq!(Key::parse("F5"), Ok(key(KeyCode::F(5), KeyModifiers::NONE)));
+        assert_eq!(
+            Key::parse("pageup"),
+            Ok(key(KeyCode::PageUp, KeyModifiers::NONE))
+        );
+        assert!(Key::parse("Hyper-x").is_err());
+        assert!(Key::parse("Ctrl-xy").is_err());
+        assert!(Key::parse("Fx").is_err());
+        assert!(Key::parse_sequence("  ").is_err());
+    }
+
+    #[test]
+    fn terminal_events_match_parsed_keys() {
+        // Alt-Shift-f 在终端里是带 Alt 的大写 F
+        let event = KeyEvent::new(KeyCode::Char('F'), KeyModifiers::ALT | KeyModifiers::SHIFT);
+        assert_eq!(Key::from(event), Key::parse("Alt-F").unwrap());
+        let event = KeyEvent::new(KeyCode::Char('A'), KeyModifiers::SHIFT);
+        assert_eq!(Key::from(event).as_char(), Some('A'));
+    }
+
+    #[test]
+    fn display_round_trip() {
+        for text in [
+            "Ctrl-S",
+            "Alt-f",
+            "Alt-F",
+            "Ctrl-Alt-Delete",
+            "Shift-Tab",
+            "Ctrl-Space",
+            "F12",
+            "PageDown",
+            "x",
+            "Ctrl-X Ctrl-S",
+        ] {
+            let keys = Key::parse_sequence(text).unwrap();
+            assert_eq!(sequence_to_string(&keys), text);
+            assert_eq!(Key::parse_sequence(&sequence_to_string(&keys)), Ok(keys));
+        }
+    }
+
+    #[test]
+    fn lookup_chords() {
+        let mut keymap = Keymap::default();
+        keymap.bind("Ctrl-X Ctrl-S", "save").unwrap();
+        let prefix = Key::parse_sequence("Ctrl-X").unwrap();
+        assert!(matches!(keymap.lookup(&prefix), Lookup::Pending));
+        assert_eq!(
+            command(&keymap, "Ctrl-X Ctrl-S"),
+            Some(Command::System(System::Save))
+        );
+        let unbound = Key::parse_sequence("Ctrl-X Ctrl-Z").unwrap();
+        assert!(matches!(keymap.lookup(&unbound), Lookup::Unbound));
+        assert_eq!(command(&keymap, "Up"), Some(Command::Move(Move::Up)));
+    }
+
+    #[test]
+    fn bind_removes_prefix_conflicts() {
+        let mut keymap = Keymap::default();
+        keymap.bind("Ctrl-K Ctrl-K", "delete_line").unwrap();
+        // 新的组合键以 Ctrl-K 开头, 原来的单键绑定被移除
+        let prefix = Key::parse_sequence("Ctrl-K").unwrap();
+        assert!(matches!(keymap.lookup(&prefix), Lookup::Pending));
+
+        keymap.bind("Ctrl-K", "kill_to_end_of_line").unwrap();
+        assert_eq!(
+            command(&keymap, "Ctrl-K"),
+            Some(Command::Edit(Edit::KillToEndOfLine))
+        );
+        assert_eq!(command(&keymap, "Ctrl-K Ctrl-K"), None);
+    }
+
+    #[test]
+    fn bind_none_unbinds() {
+        let mut keymap = Keymap::default();
+        keymap.bind("Ctrl-S", "none").unwrap();
+        let keys = Key::parse_sequence("Ctrl-S").unwrap();
+        assert!(matches!(keymap.lookup(&keys), Lookup::Unbound));
+        assert_eq!(keymap.key_for(Command::System(System::Save)), None);
+        assert!(keymap.bind("Ctrl-S", "no_such_command").is_err());
+    }
+
+    #[test]
+    fn presets() {
+        let emacs = Keymap::new(Preset::Emacs);
+        assert_eq!(command(&emacs, "Ctrl-N"), Some(Command::Move(Move::Down)));
+        assert_eq!(command(&emacs, "Ctrl-Q"), None);
+        assert_eq!(
+            emacs.key_for(Command::System(System::Quit)).as_deref(),
+            Some("Ctrl-X Ctrl-C")
+        );
+        let default = Keymap::default();
+        assert_eq!(
+            command(&default, "Ctrl-N"),
+            Some(Command::System(System::NextBuffer))
+        );
+        assert_eq!(Preset::from_name(" Vim "), Some(Preset::Vi));
+        assert_eq!(Preset::from_name("nano"), None);
+    }
+}
@@ -0,0 +1,40 @@
+use std::collections::VecDeque;
+
+const KILL_RING_SIZE: usize = 30;
+
+#[derive(Default)]
+pub struct KillRing {
+    entries: VecDeque<String>,
+}
+
+impl KillRing {
+    pub fn push(&mut self, text: String) {
+        if text.is_empty() {
+            return;
+        }
+        self.entries.push_front(text);
+        self.entries.truncate(KILL_RING_SIZE);
+    }
+
+    pub fn latest(&self) -> Option<&str> {
+        self.entries.front().map(String::as_str)
+    }
+
+    // 下标超出时从最新的一条重新开始
+    pub fn get(&self, index: usize) -> Option<&str> {
+        let len = self.entries.len();
+        if len == 0 {
+            return None;
+        }
+        self.entries.get(index % len).map(String::as_str)
+    }
+
+    // 连续删除合并为一条, 向后删除的文本放在前面
+    pub fn append(&mut self, text: String, before: bool) {
+        match self.entries.front_mut() {
+            Some(latest) if before => latest.insert_str(0, &text),
+            Some(latest) => latest.push_str(&text),
+            None => self.push(text),
+        }
+    }
+}
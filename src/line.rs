@@ -1,19 +1,28 @@
+use crate::{
+    highlight::{Span, TokenType},
+    theme::Theme,
+};
 use std::{fmt::Display, ops::Range};
 use unicode_segmentation::UnicodeSegmentation;
 use unicode_width::UnicodeWidthStr;
 
+pub const DEFAULT_TAB_WIDTH: usize = 4;
+
 #[derive(Debug, Clone, Copy, Default)]
 pub enum GraphemeWidth {
     Half,
     #[default]
     Full,
+    Tab,
 }
 
 impl GraphemeWidth {
-    const fn saturating_add(self, other: usize) -> usize {
+    const fn width_at(self, column: usize, tab_width: usize) -> usize {
         match self {
-            GraphemeWidth::Half => other.saturating_add(1),
-            GraphemeWidth::Full => other.saturating_add(2),
+            GraphemeWidth::Half => 1,
+            GraphemeWidth::Full => 2,
+            // 制表符一直延伸到下一个制表位
+            GraphemeWidth::Tab => tab_width.saturating_sub(column % tab_width),
         }
     }
 }
@@ -25,18 +34,37 @@ struct TextFragment {
     replacement: Option<char>,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct Line {
     fragments: Vec<TextFragment>,
+    tab_width: usize,
+}
+
+impl Default for Line {
+    fn default() -> Self {
+        Self {
+            fragments: Vec::new(),
+            tab_width: DEFAULT_TAB_WIDTH,
+        }
+    }
 }
 
 impl Line {
     pub fn from(line_str: &str) -> Self {
         Self {
             fragments: Self::str_to_fragments(line_str),
+            ..Self::default()
         }
     }
 
+    pub fn set_tab_width(&mut self, tab_width: usize) {
+        self.tab_width = tab_width.max(1);
+    }
+
+    fn fragment_width(&self, fragment: &TextFragment, column: usize) -> usize {
+        fragment.render_width.width_at(column, self.tab_width)
+    }
+
     fn str_to_fragments(str: &str) -> Vec<TextFragment> {
         str.graphemes(true)
             .map(|s| {
@@ -49,7 +77,14 @@ impl Line {
                         };
                         (None, rendered_width)
                     },
-                    |replacement| (Some(replacement), GraphemeWidth::Half),
+                    |replacement| {
+                        let render_width = if s == "\t" {
+                            GraphemeWidth::Tab
+                        } else {
+                            GraphemeWidth::Half
+                        };
+                        (Some(replacement), render_width)
+                    },
                 );
                 TextFragment {
                     render_width,
@@ -60,18 +95,32 @@ impl Line {
             .collect::<Vec<TextFragment>>()
     }
     pub fn get(&self, range: Range<usize>) -> String {
+        self.render(range, &[], None)
+    }
+
+    pub fn get_highlighted(&self, range: Range<usize>, spans: &[Span], theme: &Theme) -> String {
+        self.render(range, spans, Some(theme))
+    }
+
+    fn render(&self, range: Range<usize>, spans: &[Span], theme: Option<&Theme>) -> String {
         if range.start > range.end {
             return String::new();
         }
-        let mut result = String::new();
-        let mut current_pos = 0;
-        for str in &self.fragments {
-            let str_end = str.render_width.saturating_add(current_pos);
+        let mut result = StyledText::new(theme);
+        let mut current_pos: usize = 0;
+        for (index, str) in self.fragments.iter().enumerate() {
+            result.set_token(Self::token_at(spans, index));
+            let str_end = current_pos.saturating_add(self.fragment_width(str, current_pos));
             if current_pos >= range.end {
                 break;
             }
             if str_end > range.start {
-                if str_end > range.end || current_pos < range.start {
+                if matches!(str.render_width, GraphemeWidth::Tab) {
+                    let visible = str_end
+                        .min(range.end)
+                        .saturating_sub(current_pos.max(range.start));
+                    result.push_str(&" ".repeat(visible));
+                } else if str_end > range.end || current_pos < range.start {
                     result.push('⋯');
                 } else if let Some(replacement) = str.replacement {
                     result.push(replacement);
@@ -81,7 +130,64 @@ impl Line {
             }
             current_pos = str_end
         }
-        result
+        result.finish()
+    }
+
+    pub fn get_graphemes(&self, range: Range<usize>, spans: &[Span], theme: &Theme) -> String {
+        let mut column = self.width_until(range.start);
+        let mut result = StyledText::new(Some(theme));
+        for (index, fragment) in self
+            .fragments
+            .iter()
+            .enumerate()
+            .take(range.end)
+            .skip(range.start)
+        {
+            result.set_token(Self::token_at(spans, index));
+            let width = self.fragment_width(fragment, column);
+            if matches!(fragment.render_width, GraphemeWidth::Tab) {
+                result.push_str(&" ".repeat(width));
+            } else if let Some(replacement) = fragment.replacement {
+                result.push(replacement);
+            } else {
+                result.push_str(&fragment.content);
+            }
+            column = column.saturating_add(width);
+        }
+        result.finish()
+    }
+
+    fn token_at(spans: &[Span], index: usize) -> Option<TokenType> {
+        spans
+            .iter()
+            .find(|span| span.range.contains(&index))
+            .map(|span| span.token)
+    }
+
+    pub fn wrap(&self, width: usize, continuation_width: usize) -> Vec<usize> {
+        let columns = self.columns();
+        let mut starts = vec![0];
+        let mut available = width.max(1);
+        let mut row_start = 0;
+        let mut last_break = None;
+        for (index, fragment) in self.fragments.iter().enumerate() {
+            let fragment_end = columns.get(index.saturating_add(1)).copied().unwrap_or(0);
+            let row_column = |start: usize| columns.get(start).copied().unwrap_or(0);
+            while fragment_end.saturating_sub(row_column(row_start)) > available
+                && index > row_start
+            {
+                // 优先在单词边界换行, 单词太长时直接截断
+                let break_at = last_break.filter(|at| *at > row_start).unwrap_or(index);
+                starts.push(break_at);
+                row_start = break_at;
+                available = continuation_width.max(1);
+                last_break = None;
+            }
+            if fragment.content.trim().is_empty() {
+                last_break = Some(index.saturating_add(1));
+            }
+        }
+        starts
     }
 
     pub fn len(&self) -> usize {
@@ -115,13 +221,126 @@ impl Line {
         self.fragments
             .iter()
             .take(index)
-            .map(|c| match c.render_width {
-                GraphemeWidth::Full => 2,
-                GraphemeWidth::Half => 1,
+            .fold(0, |column, fragment| {
+                column.saturating_add(self.fragment_width(fragment, column))
+            })
+    }
+
+    pub fn grapheme_at_column(&self, column: usize) -> usize {
+        let mut end: usize = 0;
+        for (index, fragment) in self.fragments.iter().enumerate() {
+            end = end.saturating_add(self.fragment_width(fragment, end));
+            if end > column {
+                return index;
+            }
+        }
+        self.len()
+    }
+
+    fn columns(&self) -> Vec<usize> {
+        let mut columns = Vec::with_capacity(self.len().saturating_add(1));
+        let mut column: usize = 0;
+        columns.push(column);
+        for fragment in &self.fragments {
+            column = column.saturating_add(self.fragment_width(fragment, column));
+            columns.push(column);
+        }
+        columns
+    }
+
+    pub fn indent_end(&self) -> usize {
+        self.fragments
+            .iter()
+            .position(|fragment| fragment.content != " " && fragment.content != "\t")
+            .unwrap_or(self.len())
+    }
+
+    fn word_ranges(&self) -> Vec<Range<usize>> {
+        let text = self.to_string();
+        text.split_word_bound_indices()
+            .filter(|(_, word)| word.chars().any(char::is_alphanumeric))
+            .map(|(start, word)| {
+                self.byte_to_grapheme_index(start)
+                    ..self.byte_to_grapheme_index(start.saturating_add(word.len()))
+            })
+            .collect()
+    }
+
+    pub fn next_word_end(&self, from: usize) -> Option<usize> {
+        self.word_ranges()
+            .into_iter()
+            .find(|word| word.end > from)
+            .map(|word| word.end)
+    }
+
+    pub fn previous_word_start(&self, from: usize) -> Option<usize> {
+        self.word_ranges()
+            .into_iter()
+            .rev()
+            .find(|word| word.start < from)
+            .map(|word| word.start)
+    }
+
+    pub fn char_at(&self, index: usize) -> Option<char> {
+        self.fragments
+            .get(index)
+            .and_then(|fragment| fragment.content.chars().next())
+    }
+
+    pub fn slice(&self, range: Range<usize>) -> String {
+        let end = range.end.min(self.len());
+        self.fragments
+            .get(range.start.min(end)..end)
+            .unwrap_or_default()
+            .iter()
+            .map(|fragment| fragment.content.as_str())
+            .collect()
+    }
+
+    pub fn is_blank(&self) -> bool {
+        self.indent_end() == self.len()
+    }
+
+    pub fn find_all(&self, query: &str) -> Vec<Range<usize>> {
+        if query.is_empty() {
+            return Vec::new();
+        }
+        self.to_string()
+            .match_indices(query)
+            .map(|(byte_index, found)| {
+                self.byte_to_grapheme_index(byte_index)
+                    ..self.byte_to_grapheme_index(byte_index.saturating_add(found.len()))
             })
+            .collect()
+    }
+
+    pub fn search_forward(&self, query: &str, from_grapheme_index: usize) -> Option<usize> {
+        let text = self.to_string();
+        let start = self.grapheme_to_byte_index(from_grapheme_index);
+        text.get(start..)?
+            .find(query)
+            .map(|byte_index| self.byte_to_grapheme_index(start.saturating_add(byte_index)))
+    }
+
+    pub fn grapheme_to_byte_index(&self, index: usize) -> usize {
+        self.fragments
+            .iter()
+            .take(index)
+            .map(|fragment| fragment.content.len())
             .sum()
     }
 
+    pub fn byte_to_grapheme_index(&self, byte_index: usize) -> usize {
+        let mut current = 0;
+        for (index, fragment) in self.fragments.iter().enumerate() {
+            if current >= byte_index {
+                return index;
+            }
+            current = current.saturating_add(fragment.content.len());
+        }
+        self.len()
+    }
+
     pub fn intert_char(&mut self, s: char, idx: usize) {
         let mut result = String::new();
         for (index, str) in self.fragments.iter().enumerate() {
@@ -154,6 +373,30 @@ impl Line {
         self.delete(self.len().saturating_sub(1));
     }
 
+    pub fn delete_last_word(&mut self) {
+        let start = self.previous_word_start(self.len()).unwrap_or(0);
+        self.drain(start..self.len());
+    }
+
+    pub fn drain(&mut self, range: Range<usize>) -> String {
+        let end = range.end.min(self.len());
+        let start = range.start.min(end);
+        let removed = self
+            .fragments
+            .drain(start..end)
+            .map(|fragment| fragment.content)
+            .collect();
+        // 删除后相邻的字符可能组成新的字素簇
+        self.fragments = Self::str_to_fragments(&self.to_string());
+        removed
+    }
+
+    pub fn insert_str(&mut self, text: &str, idx: usize) {
+        let mut result = self.to_string();
+        result.insert_str(self.grapheme_to_byte_index(idx), text);
+        self.fragments = Self::str_to_fragments(&result);
+    }
+
     pub fn append(&mut self, other: &Self) {
         let mut tmp_str = self.to_string();
         tmp_str.push_str(&other.to_string());
@@ -162,10 +405,16 @@ impl Line {
 
     pub fn split(&mut self, at: usize) -> Self {
         if at >= self.len() {
-            return Self::default();
+            return Self {
+                fragments: Vec::new(),
+                tab_width: self.tab_width,
+            };
         }
         let result = self.fragments.split_off(at);
-        Self { fragments: result }
+        Self {
+            fragments: result,
+            tab_width: self.tab_width,
+        }
     }
 }
 
@@ -175,3 +424,53 @@ impl Display for Line {
         write!(f, "{tmp}")
     }
 }
+
+struct StyledText<'a> {
+    theme: Option<&'a Theme>,
+    result: String,
+    pending: String,
+    token: Option<TokenType>,
+}
+
+impl<'a> StyledText<'a> {
+    const fn new(theme: Option<&'a Theme>) -> Self {
+        Self {
+            theme,
+            result: String::new(),
+            pending: String::new(),
+            token: None,
+        }
+    }
+
+    // 相同类型的连续字素合并后一起上色
+    fn set_token(&mut self, token: Option<TokenType>) {
+        if token != self.token {
+            self.flush();
+            self.token = token;
+        }
+    }
+
+    fn push(&mut self, ch: char) {
+        self.pending.push(ch);
+    }
+
+    fn push_str(&mut self, str: &str) {
+        self.pending.push_str(str);
+    }
+
+    fn flush(&mut self) {
+        let pending = std::mem::take(&mut self.pending);
+        match self.theme {
+            Some(theme) if !pending.is_empty() => {
+                let styled = theme.token(self.token).apply(pending);
+                self.result.push_str(&styled.to_string());
+            }
+            _ => self.result.push_str(&pending),
+        }
+    }
+
+    fn finish(mut self) -> String {
+        self.flush();
+        self.result
+    }
+}
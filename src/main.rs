@@ -1,19 +1,57 @@
+mod args;
 mod buffer;
 mod command;
 mod commandbar;
+mod config;
+mod directory;
 mod documentstatus;
 mod editor;
+mod ex;
 mod fileinfo;
+mod filetype;
+mod follow;
+mod gutter;
+mod highlight;
+mod indent;
+mod keymap;
+mod killring;
 mod line;
 mod messagebar;
+mod pager;
+mod paths;
+mod picker;
 mod position;
+mod recent;
+mod session;
 mod size;
 mod statusbar;
+mod syntaxtree;
 mod terminal;
+mod theme;
 mod uicomponent;
+mod vi;
 mod view;
 
+use args::Args;
+use editor::NAME;
+
 fn main() {
-    let mut edi = editor::Editor::new().unwrap();
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{NAME}: {err}");
+            eprintln!("Try '{NAME} --help' for more information.");
+            std::process::exit(2);
+        }
+    };
+    if args.help {
+        print!("{}", Args::usage());
+        return;
+    }
+    if args.version {
+        println!("{}", Args::version());
+        return;
+    }
+    let mut edi = editor::Editor::new(&args).unwrap();
     edi.run();
 }
@@ -1,8 +1,8 @@
 use std::time::{Duration, Instant};
 
-use crate::{size::Size, terminal::Terminal, uicomponent::UIComponent};
+use crate::{size::Size, terminal::Terminal, theme::Theme, uicomponent::UIComponent};
 
-static DEFAULT_DURATION: Duration = Duration::new(5, 0);
+pub const DEFAULT_DURATION: Duration = Duration::new(5, 0);
 
 pub struct Message {
     content: String,
@@ -19,16 +19,29 @@ impl Default for Message {
 }
 
 impl Message {
-    pub fn is_expired(&self) -> bool {
-        Instant::now().duration_since(self.time) > DEFAULT_DURATION
+    pub fn is_expired(&self, duration: Duration) -> bool {
+        Instant::now().duration_since(self.time) > duration
     }
 }
 
-#[derive(Default)]
 pub struct MessageBar {
     message: Message,
     need_redraw: bool,
     cleared_after_expiry: bool,
+    theme: Theme,
+    duration: Duration,
+}
+
+impl Default for MessageBar {
+    fn default() -> Self {
+        Self {
+            message: Message::default(),
+            need_redraw: false,
+            cleared_after_expiry: false,
+            theme: Theme::default(),
+            duration: DEFAULT_DURATION,
+        }
+    }
 }
 
 impl MessageBar {
@@ -40,6 +53,15 @@ impl MessageBar {
         self.cleared_after_expiry = false;
         self.mark_redraw(true);
     }
+
+    pub fn set_duration(&mut self, duration: Duration) {
+        self.duration = duration;
+    }
+
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+        self.mark_redraw(true);
+    }
 }
 
 impl UIComponent for MessageBar {
@@ -48,20 +70,20 @@ impl UIComponent for MessageBar {
     }
 
     fn needs_redraw(&self) -> bool {
-        (!self.cleared_after_expiry && !self.message.is_expired()) || self.need_redraw
+        (!self.cleared_after_expiry && !self.message.is_expired(self.duration)) || self.need_redraw
     }
 
     fn set_size(&mut self, _: Size) {}
 
     fn draw(&mut self, origin_y: usize) -> Result<(), std::io::Error> {
-        if self.message.is_expired() {
+        if self.message.is_expired(self.duration) {
             self.cleared_after_expiry = true;
         }
-        let message = if self.message.is_expired() {
+        let message = if self.message.is_expired(self.duration) {
             ""
         } else {
             &self.message.content
         };
-        Terminal::print_row(origin_y, &message)
+        Terminal::print_styled_row(origin_y, message, self.theme.message_bar)
     }
 }
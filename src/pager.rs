@@ -0,0 +1,105 @@
+use crate::command::{Edit, Move};
+
+#[derive(Debug, Clone, Copy)]
+pub enum PagerAction {
+    Move(Move),
+    Top,
+    Bottom,
+    Search,
+    SearchNext,
+    Follow,
+    Quit,
+}
+
+impl TryFrom<Edit> for PagerAction {
+    type Error = String;
+    fn try_from(edit: Edit) -> Result<Self, Self::Error> {
+        match edit {
+            Edit::Insert(' ' | 'f') => Ok(Self::Move(Move::PageDown)),
+            Edit::Insert('b') => Ok(Self::Move(Move::PageUp)),
+            Edit::Insert('j') | Edit::InsertNewline => Ok(Self::Move(Move::Down)),
+            Edit::Insert('k') => Ok(Self::Move(Move::Up)),
+            Edit::Insert('g' | '<') => Ok(Self::Top),
+            Edit::Insert('G' | '>') => Ok(Self::Bottom),
+            Edit::Insert('/') => Ok(Self::Search),
+            Edit::Insert('n') => Ok(Self::SearchNext),
+            Edit::Insert('F') => Ok(Self::Follow),
+            Edit::Insert('q' | 'Q') => Ok(Self::Quit),
+            _ => Err(format!("Unsupported pager key {edit:?}")),
+        }
+    }
+}
+
+pub fn strip_escape_sequences(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch != '\u{1b}' {
+            result.push(ch);
+            continue;
+        }
+        match chars.next() {
+            // CSI 序列: ESC [ 参数 结束字节
+            Some('[') => {
+                for next in chars.by_ref() {
+                    if ('@'..='~').contains(&next) {
+                        break;
+                    }
+                }
+            }
+            // OSC 等字符串序列以 BEL 或 ESC \ 结束
+            Some(']' | 'P' | '_' | '^') => {
+                while let Some(next) = chars.next() {
+                    if next == '\u{7}' {
+                        break;
+                    }
+                    if next == '\u{1b}' {
+                        if chars.peek() == Some(&'\\') {
+                            chars.next();
+                        }
+                        break;
+                    }
+                }
+            }
+            // ESC ( B 这类带中间字节的序列
+            Some(' '..='/') => {
+                for next in chars.by_ref() {
+                    if !(' '..='/').contains(&next) {
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_csi() {
+        assert_eq!(strip_escape_sequences("\u{1b}[1;31mred\u{1b}[0m"), "red");
+        assert_eq!(strip_escape_sequences("a\u{1b}[2Kb\u{1b}[?25hc"), "abc");
+        assert_eq!(strip_escape_sequences("plain text"), "plain text");
+        // 未结束的序列直接丢掉
+        assert_eq!(strip_escape_sequences("a\u{1b}[31"), "a");
+    }
+
+    #[test]
+    fn strip_osc() {
+        let link = "\u{1b}]8;;https://example.com\u{1b}\\link\u{1b}]8;;\u{1b}\\";
+        assert_eq!(strip_escape_sequences(link), "link");
+        assert_eq!(strip_escape_sequences("\u{1b}]0;title\u{7}text"), "text");
+        assert_eq!(strip_escape_sequences("a\u{1b}]0;title"), "a");
+    }
+
+    #[test]
+    fn strip_other_escapes() {
+        assert_eq!(strip_escape_sequences("\u{1b}(Bx\u{1b}=y"), "xy");
+        assert_eq!(strip_escape_sequences("a\u{1b}"), "a");
+        assert_eq!(strip_escape_sequences("\u{1b}P1$r\u{1b}\\ok"), "ok");
+    }
+}
@@ -0,0 +1,255 @@
+use crate::{
+    command::{Edit, Move},
+    line::Line,
+    size::Size,
+    terminal::Terminal,
+    theme::Theme,
+    uicomponent::UIComponent,
+};
+
+#[derive(Default)]
+pub struct Picker {
+    title: String,
+    items: Vec<String>,
+    matches: Vec<usize>,
+    query: Line,
+    selected: usize,
+    scroll_top: usize,
+    size: Size,
+    need_redraw: bool,
+    theme: Theme,
+}
+
+impl Picker {
+    pub fn new(title: &str, items: Vec<String>, theme: Theme) -> Self {
+        let mut picker = Self {
+            title: title.to_string(),
+            items,
+            theme,
+            ..Self::default()
+        };
+        picker.update_matches();
+        picker
+    }
+
+    pub fn handle_edit(&mut self, edit: Edit) {
+        match edit {
+            Edit::Insert(c) => self.query.append_char(c),
+            Edit::DeleteBackward => self.query.delete_last(),
+            Edit::DeleteWordBackward => self.query.delete_last_word(),
+            Edit::KillToStartOfLine | Edit::DeleteLine => self.query = Line::default(),
+            Edit::Delete
+            | Edit::InsertNewline
+            | Edit::DeleteWordForward
+            | Edit::KillToEndOfLine => return,
+        }
+        self.update_matches();
+    }
+
+    pub fn handle_move(&mut self, mv: Move) {
+        let page = self.list_height().saturating_sub(1).max(1);
+        self.selected = match mv {
+            Move::Up => self.selected.saturating_sub(1),
+            Move::Down => self.selected.saturating_add(1),
+            Move::PageUp => self.selected.saturating_sub(page),
+            Move::PageDown => self.selected.saturating_add(page),
+            Move::Home | Move::StartOfDocument => 0,
+            Move::End | Move::EndOfDocument => self.matches.len(),
+            Move::Left
+            | Move::Right
+            | Move::WordLeft
+            | Move::WordRight
+            | Move::ParagraphUp
+            | Move::ParagraphDown
+            | Move::ParentNode
+            | Move::NextSibling
+            | Move::PreviousSibling => self.selected,
+        }
+        .min(self.matches.len().saturating_sub(1));
+        self.scroll_selected_into_view();
+        self.mark_redraw(true);
+    }
+
+    pub fn selected_item(&self) -> Option<usize> {
+        self.matches.get(self.selected).copied()
+    }
+
+    pub fn caret_position_col(&self) -> usize {
+        Line::from(&self.title)
+            .width()
+            .saturating_add(self.query.width())
+            .min(self.size.width)
+    }
+
+    fn update_matches(&mut self) {
+        let query = self.query.to_string().to_lowercase();
+        let mut scored: Vec<(usize, usize)> = self
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(index, item)| {
+                fuzzy_score(&item.to_lowercase(), &query).map(|score| (index, score))
+            })
+            .collect();
+        // 得分相同时保持原有顺序
+        scored.sort_by(|(_, a), (_, b)| b.cmp(a));
+        self.matches = scored.into_iter().map(|(index, _)| index).collect();
+        self.selected = 0;
+        self.scroll_top = 0;
+        self.mark_redraw(true);
+    }
+
+    fn list_height(&self) -> usize {
+        self.size.height.saturating_sub(1)
+    }
+
+    fn scroll_selected_into_view(&mut self) {
+        let height = self.list_height();
+        if self.selected < self.scroll_top {
+            self.scroll_top = self.selected;
+        } else if self.selected >= self.scroll_top.saturating_add(height) {
+            self.scroll_top = self.selected.saturating_sub(height).saturating_add(1);
+        }
+    }
+}
+
+// 按顺序包含查询的全部字符即匹配, 连续的字符和词首的字符得分更高
+fn fuzzy_score(item: &str, query: &str) -> Option<usize> {
+    let chars: Vec<char> = item.chars().collect();
+    // 优先匹配词首可能导致后面的字符匹配不上, 这时退回逐个匹配
+    match_chars(&chars, query, true).or_else(|| match_chars(&chars, query, false))
+}
+
+fn match_chars(chars: &[char], query: &str, prefer_word_start: bool) -> Option<usize> {
+    let is_word_start = |index: usize| {
+        index
+            .checked_sub(1)
+            .and_then(|prev| chars.get(prev))
+            .is_none_or(|prev| !prev.is_alphanumeric())
+    };
+    let mut score = 0usize;
+    let mut next = 0;
+    for wanted in query.chars() {
+        let first = (next..chars.len()).find(|&index| chars[index] == wanted)?;
+        let consecutive = next > 0 && first == next;
+        let index = if prefer_word_start && !consecutive {
+            (first..chars.len())
+                .find(|&index| chars[index] == wanted && is_word_start(index))
+                .unwrap_or(first)
+        } else {
+            first
+        };
+        score = score
+            .saturating_add(1)
+            .saturating_add(if next > 0 && index == next { 2 } else { 0 })
+            .saturating_add(if is_word_start(index) { 2 } else { 0 });
+        next = index.saturating_add(1);
+    }
+    Some(score)
+}
+
+impl UIComponent for Picker {
+    fn mark_redraw(&mut self, redraw: bool) {
+        self.need_redraw = redraw;
+    }
+
+    fn needs_redraw(&self) -> bool {
+        self.need_redraw
+    }
+
+    fn set_size(&mut self, size: Size) {
+        self.size = size;
+        self.scroll_selected_into_view();
+    }
+
+    fn draw(&mut self, origin_y: usize) -> Result<(), std::io::Error> {
+        let Size { width, height } = self.size;
+        let query = format!("{}{}", self.title, self.query);
+        let text = self.theme.text;
+        Terminal::print_styled_row(origin_y, &Line::from(&query).get(0..width), text)?;
+        for row in 1..height {
+            let match_index = self.scroll_top.saturating_add(row).saturating_sub(1);
+            let at = origin_y.saturating_add(row);
+            match self
+                .matches
+                .get(match_index)
+                .and_then(|i| self.items.get(*i))
+            {
+                Some(item) if match_index == self.selected => {
+                    let selection = self.theme.selection();
+                    Terminal::print_filled_row(at, &Line::from(item).get(0..width), selection)?;
+                }
+                Some(item) => {
+                    Terminal::print_styled_row(at, &Line::from(item).get(0..width), text)?;
+                }
+                None => Terminal::print_styled_row(at, "", text)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(items: &[&str], query: &str) -> Vec<usize> {
+        let items = items.iter().map(ToString::to_string).collect();
+        let mut picker = Picker::new("", items, Theme::default());
+        for ch in query.chars() {
+            picker.handle_edit(Edit::Insert(ch));
+        }
+        picker.matches
+    }
+
+    #[test]
+    fn scores() {
+        assert_eq!(fuzzy_score("delete_line", ""), Some(0));
+        assert_eq!(fuzzy_score("delete_line", "dl"), Some(6));
+        assert_eq!(fuzzy_score("fold_all", "dl"), Some(2));
+        assert_eq!(fuzzy_score("dl", "dl"), Some(6));
+        assert_eq!(fuzzy_score("undo", "dl"), None);
+        assert_eq!(fuzzy_score("abc", "abcd"), None);
+    }
+
+    #[test]
+    fn caret_after_wide_title() {
+        let mut picker = Picker::new("文件: ", Vec::new(), Theme::default());
+        picker.set_size(Size {
+            width: 80,
+            height: 10,
+        });
+        picker.handle_edit(Edit::Insert('a'));
+        assert_eq!(picker.caret_position_col(), 7);
+    }
+
+    #[test]
+    fn word_start_fallback() {
+        // 跳到词首后剩下的字符匹配不上, 退回逐个匹配
+        assert_eq!(fuzzy_score("xab_a", "ab"), Some(4));
+        assert_eq!(fuzzy_score("x_ab", "ab"), Some(6));
+    }
+
+    #[test]
+    fn ordering() {
+        let items = ["fold_all", "delete_line", "undo", "dl"];
+        assert_eq!(matches(&items, "dl"), [1, 3, 0]);
+        // 忽略大小写
+        assert_eq!(matches(&items, "DL"), [1, 3, 0]);
+        // 空查询和同分时保持原有顺序
+        assert_eq!(matches(&items, ""), [0, 1, 2, 3]);
+        assert_eq!(matches(&["b_x", "a_x", "c_x"], "x"), [0, 1, 2]);
+    }
+
+    #[test]
+    fn query_edits() {
+        let items = vec![String::from("save"), String::from("quit")];
+        let mut picker = Picker::new("", items, Theme::default());
+        picker.handle_edit(Edit::Insert('q'));
+        assert_eq!(picker.selected_item(), Some(1));
+        picker.handle_edit(Edit::DeleteBackward);
+        assert_eq!(picker.selected_item(), Some(0));
+        picker.handle_edit(Edit::Insert('z'));
+        assert_eq!(picker.selected_item(), None);
+    }
+}
@@ -1,4 +1,4 @@
-#[derive(Default, Copy, Clone)]
+#[derive(Debug, Default, Copy, Clone)]
 pub struct Position {
     pub col: usize,
     pub row: usize,
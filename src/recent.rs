@@ -0,0 +1,102 @@
+use std::{
+    fs,
+    io::Error,
+    path::{Path, PathBuf},
+};
+
+use crate::{paths::state_dir, position::Position, view::Location};
+
+const MAX_ENTRIES: usize = 50;
+const FILE_NAME: &str = "recent";
+
+#[derive(Debug, Clone)]
+pub struct RecentEntry {
+    pub path: PathBuf,
+    pub location: Location,
+    pub scroll_offset: Position,
+}
+
+impl RecentEntry {
+    fn parse(line: &str) -> Option<Self> {
+        let mut parts = line.splitn(5, '\t');
+        let line_index = parts.next()?.parse().ok()?;
+        let grapheme_index = parts.next()?.parse().ok()?;
+        let row = parts.next()?.parse().ok()?;
+        let col = parts.next()?.parse().ok()?;
+        let path = PathBuf::from(parts.next()?);
+        Some(Self {
+            path,
+            location: Location {
+                grapheme_index,
+                line_index,
+            },
+            scroll_offset: Position { col, row },
+        })
+    }
+
+    fn serialize(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{}\t{}",
+            self.location.line_index,
+            self.location.grapheme_index,
+            self.scroll_offset.row,
+            self.scroll_offset.col,
+            self.path.display()
+        )
+    }
+}
+
+#[derive(Default)]
+pub struct RecentFiles {
+    entries: Vec<RecentEntry>,
+}
+
+impl RecentFiles {
+    pub fn load() -> Self {
+        let entries = state_dir()
+            .and_then(|dir| fs::read_to_string(dir.join(FILE_NAME)).ok())
+            .map(|contents| contents.lines().filter_map(RecentEntry::parse).collect())
+            .unwrap_or_default();
+        Self { entries }
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        let Some(dir) = state_dir() else {
+            return Ok(());
+        };
+        fs::create_dir_all(&dir)?;
+        let contents: String = self
+            .entries
+            .iter()
+            .map(|entry| format!("{}\n", entry.serialize()))
+            .collect();
+        fs::write(dir.join(FILE_NAME), contents)
+    }
+
+    pub fn get(&self, path: &Path) -> Option<&RecentEntry> {
+        let path = Self::normalize(path);
+        self.entries.iter().find(|entry| entry.path == path)
+    }
+
+    pub fn touch(&mut self, path: &Path, location: Location, scroll_offset: Position) {
+        let path = Self::normalize(path);
+        self.entries.retain(|entry| entry.path != path);
+        self.entries.insert(
+            0,
+            RecentEntry {
+                path,
+                location,
+                scroll_offset,
+            },
+        );
+        self.entries.truncate(MAX_ENTRIES);
+    }
+
+    pub fn entries(&self) -> &[RecentEntry] {
+        &self.entries
+    }
+
+    fn normalize(path: &Path) -> PathBuf {
+        fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+    }
+}
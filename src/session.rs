@@ -0,0 +1,143 @@
+use std::{
+    fs,
+    io::{Error, ErrorKind},
+    path::PathBuf,
+};
+
+use crate::{paths::state_dir, position::Position, view::Location};
+
+const SESSION_DIR: &str = "sessions";
+
+#[derive(Debug, Clone, Default)]
+pub struct SessionBuffer {
+    pub path: Option<PathBuf>,
+    pub location: Location,
+    pub scroll_offset: Position,
+    pub scratch: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Session {
+    pub active: usize,
+    pub buffers: Vec<SessionBuffer>,
+    pub search_history: Vec<String>,
+}
+
+impl Session {
+    pub fn load(name: &str) -> Result<Self, Error> {
+        let contents = fs::read_to_string(Self::path(name)?)?;
+        let mut session = Self::default();
+        let mut lines = contents.lines();
+        while let Some(line) = lines.next() {
+            let mut parts = line.split('\t');
+            match parts.next() {
+                Some("active") => {
+                    session.active = parts.next().and_then(|s| s.parse().ok()).unwrap_or(0);
+                }
+                Some("search") => {
+                    session
+                        .search_history
+                        .push(parts.collect::<Vec<_>>().join("\t"));
+                }
+                Some(kind @ ("buffer" | "scratch")) => {
+                    let mut numbers = [0; 4];
+                    for number in &mut numbers {
+                        *number = parts
+                            .next()
+                            .and_then(|s| s.parse().ok())
+                            .ok_or_else(|| Self::invalid(line))?;
+                    }
+                    let rest = parts.collect::<Vec<_>>().join("\t");
+                    let mut buffer = SessionBuffer {
+                        location: Location {
+                            line_index: numbers[0],
+                            grapheme_index: numbers[1],
+                        },
+                        scroll_offset: Position {
+                            row: numbers[2],
+                            col: numbers[3],
+                        },
+                        ..SessionBuffer::default()
+                    };
+                    if kind == "buffer" {
+                        buffer.path = Some(PathBuf::from(rest));
+                    } else {
+                        let count: usize = rest.parse().map_err(|_| Self::invalid(line))?;
+                        let scratch: Vec<&str> = lines.by_ref().take(count).collect();
+                        buffer.scratch = Some(scratch.join("\n"));
+                    }
+                    session.buffers.push(buffer);
+                }
+                _ => return Err(Self::invalid(line)),
+            }
+        }
+        Ok(session)
+    }
+
+    pub fn save(&self, name: &str) -> Result<(), Error> {
+        let path = Self::path(name)?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut contents = format!("active\t{}\n", self.active);
+        for query in &self.search_history {
+            contents.push_str(&format!("search\t{query}\n"));
+        }
+        for buffer in &self.buffers {
+            let Location {
+                line_index,
+                grapheme_index,
+            } = buffer.location;
+            let Position { row, col } = buffer.scroll_offset;
+            let position = format!("{line_index}\t{grapheme_index}\t{row}\t{col}");
+            if let Some(scratch) = &buffer.scratch {
+                let lines: Vec<&str> = scratch.lines().collect();
+                contents.push_str(&format!("scratch\t{position}\t{}\n", lines.len()));
+                for line in lines {
+                    contents.push_str(line);
+                    contents.push('\n');
+                }
+            } else if let Some(file) = &buffer.path {
+                contents.push_str(&format!("buffer\t{position}\t{}\n", file.display()));
+            }
+        }
+        fs::write(path, contents)
+    }
+
+    pub fn exists(name: &str) -> bool {
+        Self::path(name).is_ok_and(|path| path.is_file())
+    }
+
+    pub fn list() -> Vec<String> {
+        let mut names: Vec<String> = state_dir()
+            .and_then(|dir| fs::read_dir(dir.join(SESSION_DIR)).ok())
+            .map(|entries| {
+                entries
+                    .filter_map(Result::ok)
+                    .map(|entry| entry.file_name().to_string_lossy().to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+        names.sort();
+        names
+    }
+
+    fn path(name: &str) -> Result<PathBuf, Error> {
+        if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Invalid session name {name}"),
+            ));
+        }
+        state_dir()
+            .map(|dir| dir.join(SESSION_DIR).join(name))
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "No state directory"))
+    }
+
+    fn invalid(line: &str) -> Error {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("Invalid session line: {line}"),
+        )
+    }
+}
@@ -1,4 +1,4 @@
-#[derive(Default, Debug, Copy, Clone)]
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
 pub struct Size {
     pub width: usize,
     pub height: usize,
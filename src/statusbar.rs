@@ -1,5 +1,6 @@
 use crate::{
-    documentstatus::DocumentStatus, size::Size, terminal::Terminal, uicomponent::UIComponent,
+    documentstatus::DocumentStatus, size::Size, terminal::Terminal, theme::Theme,
+    uicomponent::UIComponent,
 };
 
 #[derive(Default)]
@@ -9,6 +10,7 @@ pub struct Statusbar {
     margin_bottom: usize,
     size: Size,
     is_visible: bool,
+    theme: Theme,
 }
 
 impl Statusbar {
@@ -18,6 +20,11 @@ impl Statusbar {
             self.mark_redraw(true);
         }
     }
+
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+        self.mark_redraw(true);
+    }
 }
 
 impl UIComponent for Statusbar {
@@ -48,8 +55,21 @@ impl UIComponent for Statusbar {
     fn draw(&mut self, origin_y: usize) -> Result<(), std::io::Error> {
         let line_count = self.current_status.line_count_to_string();
         let modified_indicator = self.current_status.modified_indicator_to_string();
+        let readonly_indicator = self.current_status.readonly_indicator_to_string();
         let filename = self.current_status.filename.clone();
-        let beginning = format!("{} {} {}", filename, modified_indicator, line_count);
+        let follow_indicator = self.current_status.follow_indicator_to_string();
+        let pending_keys = self.current_status.pending_keys_to_string();
+        let mode_indicator = self.current_status.mode_indicator_to_string();
+        let beginning = format!(
+            "{}{} {}{} {}{}{}",
+            mode_indicator,
+            filename,
+            readonly_indicator,
+            modified_indicator,
+            line_count,
+            follow_indicator,
+            pending_keys
+        );
         let position_indicator = self.current_status.position_indicator_to_string();
         let remainder_len = self.size.width.saturating_sub(beginning.len());
         let status = format!("{beginning}{position_indicator:>remainder_len$}");
@@ -58,7 +78,7 @@ impl UIComponent for Statusbar {
         } else {
             String::new()
         };
-        Terminal::print_inverted_row(origin_y, &to_print)?;
+        Terminal::print_filled_row(origin_y, &to_print, self.theme.status_bar)?;
         Ok(())
     }
 }
@@ -0,0 +1,288 @@
+use crate::filetype::FileType;
+#[cfg(not(feature = "tree-sitter"))]
+use crate::{highlight::Span, line::Line};
+#[cfg(not(feature = "tree-sitter"))]
+use std::ops::Range;
+
+#[cfg_attr(not(feature = "tree-sitter"), allow(dead_code))]
+pub struct TextEdit {
+    pub start_byte: usize,
+    pub old_end_byte: usize,
+    pub new_end_byte: usize,
+    pub start: (usize, usize),
+    pub old_end: (usize, usize),
+    pub new_end: (usize, usize),
+    pub inserted: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum NodeMotion {
+    Parent,
+    NextSibling,
+    PreviousSibling,
+}
+
+#[cfg(feature = "tree-sitter")]
+pub use parsed::SyntaxTree;
+
+#[cfg(not(feature = "tree-sitter"))]
+#[derive(Debug)]
+pub enum SyntaxTree {}
+
+#[cfg(not(feature = "tree-sitter"))]
+impl SyntaxTree {
+    pub fn for_file_type(_file_type: FileType) -> Option<Self> {
+        None
+    }
+
+    pub fn edit(&mut self, _edit: &TextEdit) {
+        match *self {}
+    }
+
+    pub fn reset(&mut self) {
+        match *self {}
+    }
+
+    pub fn highlight(&mut self, _lines: &[Line], _range: Range<usize>) {
+        match *self {}
+    }
+
+    pub fn spans(&self, _line_index: usize) -> &[Span] {
+        match *self {}
+    }
+
+    pub fn node_target(
+        &mut self,
+        _lines: &[Line],
+        _at: (usize, usize),
+        _motion: NodeMotion,
+    ) -> Option<(usize, usize)> {
+        match *self {}
+    }
+}
+
+#[cfg(feature = "tree-sitter")]
+mod parsed {
+    use super::{FileType, NodeMotion, TextEdit};
+    use crate::{
+        highlight::{Span, TokenType},
+        line::Line,
+    };
+    use std::{fmt, ops::Range};
+    use streaming_iterator::StreamingIterator;
+    use tree_sitter::{InputEdit, Language, Node, Parser, Point, Query, QueryCursor, Tree};
+
+    pub struct SyntaxTree {
+        parser: Parser,
+        query: Query,
+        tree: Option<Tree>,
+        text: String,
+        dirty: bool,
+        spans: Vec<Vec<Span>>,
+        span_range: Range<usize>,
+    }
+
+    impl fmt::Debug for SyntaxTree {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("SyntaxTree")
+                .field("dirty", &self.dirty)
+                .finish_non_exhaustive()
+        }
+    }
+
+    fn language_for(file_type: FileType) -> Option<(Language, &'static str)> {
+        let (language, query) = match file_type {
+            FileType::Rust => (
+                tree_sitter_rust::LANGUAGE,
+                tree_sitter_rust::HIGHLIGHTS_QUERY,
+            ),
+            FileType::Python => (
+                tree_sitter_python::LANGUAGE,
+                tree_sitter_python::HIGHLIGHTS_QUERY,
+            ),
+            FileType::C => (tree_sitter_c::LANGUAGE, tree_sitter_c::HIGHLIGHT_QUERY),
+            FileType::JavaScript => (
+                tree_sitter_javascript::LANGUAGE,
+                tree_sitter_javascript::HIGHLIGHT_QUERY,
+            ),
+            FileType::Go => (tree_sitter_go::LANGUAGE, tree_sitter_go::HIGHLIGHTS_QUERY),
+            FileType::Shell => (
+                tree_sitter_bash::LANGUAGE,
+                tree_sitter_bash::HIGHLIGHT_QUERY,
+            ),
+            _ => return None,
+        };
+        Some((language.into(), query))
+    }
+
+    fn token_for(capture: &str) -> Option<TokenType> {
+        let scope = capture.split('.').next().unwrap_or_default();
+        match (scope, capture) {
+            ("keyword", _) => Some(TokenType::Keyword),
+            ("type" | "constructor", _) => Some(TokenType::Type),
+            ("string" | "escape", _) => Some(TokenType::String),
+            ("comment", _) => Some(TokenType::Comment),
+            ("number", _) | (_, "constant.builtin") => Some(TokenType::Number),
+            _ => None,
+        }
+    }
+
+    fn line_start_byte(lines: &[Line], line_index: usize) -> usize {
+        lines
+            .iter()
+            .take(line_index)
+            .map(|line| line.grapheme_to_byte_index(line.len()).saturating_add(1))
+            .sum()
+    }
+
+    impl SyntaxTree {
+        pub fn for_file_type(file_type: FileType) -> Option<Self> {
+            let (language, source) = language_for(file_type)?;
+            let mut parser = Parser::new();
+            parser.set_language(&language).ok()?;
+            let query = Query::new(&language, source).ok()?;
+            Some(Self {
+                parser,
+                query,
+                tree: None,
+                text: String::new(),
+                dirty: true,
+                spans: Vec::new(),
+                span_range: 0..0,
+            })
+        }
+
+        pub fn edit(&mut self, edit: &TextEdit) {
+            let point = |(row, column): (usize, usize)| Point { row, column };
+            if let Some(tree) = &mut self.tree {
+                tree.edit(&InputEdit {
+                    start_byte: edit.start_byte,
+                    old_end_byte: edit.old_end_byte,
+                    new_end_byte: edit.new_end_byte,
+                    start_position: point(edit.start),
+                    old_end_position: point(edit.old_end),
+                    new_end_position: point(edit.new_end),
+                });
+                // 同步保存的文本, 解析时就不用重新拼接整个文档
+                let range = edit.start_byte..edit.old_end_byte;
+                if self.text.get(range.clone()).is_some() {
+                    self.text.replace_range(range, &edit.inserted);
+                } else {
+                    self.tree = None;
+                }
+            }
+            self.dirty = true;
+        }
+
+        pub fn reset(&mut self) {
+            self.tree = None;
+            self.dirty = true;
+        }
+
+        fn parse(&mut self, lines: &[Line]) {
+            if !self.dirty {
+                return;
+            }
+            // 没有旧语法树或者文本长度对不上时才重新拼接整个文档
+            let in_sync =
+                self.tree.is_some() && self.text.len() == line_start_byte(lines, lines.len());
+            if !in_sync {
+                self.tree = None;
+                self.text = lines.iter().map(|line| format!("{line}\n")).collect();
+            }
+            // 把上一次的语法树传给解析器, 只重新解析改动过的部分
+            self.tree = self.parser.parse(&self.text, self.tree.as_ref());
+            self.dirty = false;
+            self.span_range = 0..0;
+        }
+
+        pub fn highlight(&mut self, lines: &[Line], range: Range<usize>) {
+            self.parse(lines);
+            if self.span_range == range {
+                return;
+            }
+            self.spans = vec![Vec::new(); range.len()];
+            self.span_range = range.clone();
+            let Some(tree) = &self.tree else {
+                return;
+            };
+            let mut cursor = QueryCursor::new();
+            cursor.set_byte_range(
+                line_start_byte(lines, range.start)..line_start_byte(lines, range.end),
+            );
+            let names = self.query.capture_names();
+            let mut captures = cursor.captures(&self.query, tree.root_node(), self.text.as_bytes());
+            while let Some((query_match, index)) = captures.next() {
+                let Some(capture) = query_match.captures.get(*index) else {
+                    continue;
+                };
+                #[allow(clippy::as_conversions)]
+                let name = names.get(capture.index as usize).copied();
+                let Some(token) = name.and_then(token_for) else {
+                    continue;
+                };
+                let start = capture.node.start_position();
+                let end = capture.node.end_position();
+                for row in start.row.max(range.start)..=end.row.min(range.end.saturating_sub(1)) {
+                    let Some(line) = lines.get(row) else {
+                        continue;
+                    };
+                    let from = if row == start.row {
+                        line.byte_to_grapheme_index(start.column)
+                    } else {
+                        0
+                    };
+                    let to = if row == end.row {
+                        line.byte_to_grapheme_index(end.column)
+                    } else {
+                        line.len()
+                    };
+                    if let Some(spans) = self.spans.get_mut(row.saturating_sub(range.start)) {
+                        spans.push(Span {
+                            range: from..to,
+                            token,
+                        });
+                    }
+                }
+            }
+        }
+
+        pub fn spans(&self, line_index: usize) -> &[Span] {
+            if !self.span_range.contains(&line_index) {
+                return &[];
+            }
+            self.spans
+                .get(line_index.saturating_sub(self.span_range.start))
+                .map_or(&[], Vec::as_slice)
+        }
+
+        pub fn node_target(
+            &mut self,
+            lines: &[Line],
+            (row, column): (usize, usize),
+            motion: NodeMotion,
+        ) -> Option<(usize, usize)> {
+            self.parse(lines);
+            let byte = line_start_byte(lines, row).saturating_add(column);
+            let root = self.tree.as_ref()?.root_node();
+            let mut node = root.named_descendant_for_byte_range(byte, byte)?;
+            // 取从光标处开始的最大节点, 这样兄弟节点才有意义
+            while let Some(parent) = node.parent().filter(|parent| {
+                parent.start_byte() == node.start_byte() && parent.id() != root.id()
+            }) {
+                node = parent;
+            }
+            let target: Option<Node> = match motion {
+                // 光标在节点中间时先回到该节点的开头
+                NodeMotion::Parent | NodeMotion::PreviousSibling if node.start_byte() < byte => {
+                    Some(node)
+                }
+                NodeMotion::Parent => node.parent(),
+                NodeMotion::NextSibling => node.next_named_sibling(),
+                NodeMotion::PreviousSibling => node.prev_named_sibling(),
+            };
+            let position = target?.start_position();
+            Some((position.row, position.column))
+        }
+    }
+}
@@ -1,8 +1,8 @@
-use crate::{position::Position, size::Size};
+use crate::{line::Line, position::Position, size::Size};
 use crossterm::{
     cursor::{Hide, MoveTo, Show},
     queue,
-    style::{Attribute, Print},
+    style::{Attribute, ContentStyle, Print, ResetColor, SetAttribute, SetStyle},
     terminal::{
         disable_raw_mode, enable_raw_mode, size, Clear, ClearType, DisableLineWrap, EnableLineWrap,
         EnterAlternateScreen, LeaveAlternateScreen, SetTitle,
@@ -36,17 +36,18 @@ impl Terminal {
         Self::queue_comand(SetTitle(title))
     }
 
-    pub fn print_inverted_row(row: usize, line_txt: &str) -> Result<(), Error> {
+    pub fn print_filled_row(row: usize, line_txt: &str, style: ContentStyle) -> Result<(), Error> {
         let width = Self::size()?.width;
-        Self::print_row(
-            row,
-            &format!(
-                "{}{:width$.width$}{}",
-                Attribute::Reverse,
-                line_txt,
-                Attribute::Reset
-            ),
-        )
+        Self::print_styled_row(row, &fill_to_width(line_txt, width), style)
+    }
+
+    pub fn print_styled_row(row: usize, line_txt: &str, style: ContentStyle) -> Result<(), Error> {
+        // 先设置样式再清行, 这样行尾空白也会填上背景色
+        Self::queue_comand(SetStyle(style))?;
+        Self::print_row(row, &style.apply(line_txt).to_string())?;
+        Self::queue_comand(ResetColor)?;
+        Self::queue_comand(SetAttribute(Attribute::Reset))?;
+        Ok(())
     }
 
     pub fn hide_caret() -> Result<(), Error> {
@@ -118,3 +119,26 @@ impl Terminal {
         Ok(())
     }
 }
+
+// 按显示宽度截断并用空格补齐, 宽字符占两列
+fn fill_to_width(line_txt: &str, width: usize) -> String {
+    let line = Line::from(line_txt);
+    let mut result = line.get(0..width);
+    let padding = width.saturating_sub(line.width().min(width));
+    result.push_str(&" ".repeat(padding));
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use unicode_width::UnicodeWidthStr;
+
+    #[test]
+    fn fills_by_display_width() {
+        assert_eq!(fill_to_width("ab", 4), "ab  ");
+        assert_eq!(fill_to_width("文件", 6), "文件  ");
+        assert_eq!(fill_to_width("文件名", 5).width(), 5);
+        assert_eq!(fill_to_width("abcdef", 3), "abc");
+    }
+}
@@ -0,0 +1,356 @@
+use crate::{highlight::TokenType, paths};
+use crossterm::style::{Attribute, Color, ContentStyle};
+use std::{env, fs};
+
+pub const DEFAULT_THEME: &str = "default";
+
+const BUILTIN_THEMES: &[(&str, &str)] = &[
+    (
+        "default",
+        "status_bar = reverse
+selection = reverse
+search_match = black on yellow
+keyword = magenta
+type = cyan
+string = green
+comment = darkgrey
+number = yellow
+",
+    ),
+    (
+        "dark",
+        "text = #abb2bf on #282c34
+status_bar = #282c34 on #61afef
+message_bar = #abb2bf on #21252b
+gutter = #5c6370
+selection = #abb2bf on #3e4451
+search_match = #282c34 on #e5c07b
+keyword = #c678dd
+type = #e5c07b
+string = #98c379
+comment = #5c6370 italic
+number = #d19a66
+",
+    ),
+];
+
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+const ANSI_COLORS: [(&str, Color, (u8, u8, u8)); 16] = [
+    ("black", Color::Black, (0, 0, 0)),
+    ("darkred", Color::DarkRed, (205, 0, 0)),
+    ("darkgreen", Color::DarkGreen, (0, 205, 0)),
+    ("darkyellow", Color::DarkYellow, (205, 205, 0)),
+    ("darkblue", Color::DarkBlue, (0, 0, 238)),
+    ("darkmagenta", Color::DarkMagenta, (205, 0, 205)),
+    ("darkcyan", Color::DarkCyan, (0, 205, 205)),
+    ("grey", Color::Grey, (229, 229, 229)),
+    ("darkgrey", Color::DarkGrey, (127, 127, 127)),
+    ("red", Color::Red, (255, 0, 0)),
+    ("green", Color::Green, (0, 255, 0)),
+    ("yellow", Color::Yellow, (255, 255, 0)),
+    ("blue", Color::Blue, (92, 92, 255)),
+    ("magenta", Color::Magenta, (255, 0, 255)),
+    ("cyan", Color::Cyan, (0, 255, 255)),
+    ("white", Color::White, (255, 255, 255)),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSupport {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+}
+
+impl ColorSupport {
+    pub fn detect() -> Self {
+        let colorterm = env::var("COLORTERM").unwrap_or_default();
+        let term = env::var("TERM").unwrap_or_default();
+        if matches!(colorterm.as_str(), "truecolor" | "24bit") {
+            Self::TrueColor
+        } else if term.contains("256color") {
+            Self::Ansi256
+        } else {
+            Self::Ansi16
+        }
+    }
+
+    fn color(self, rgb: (u8, u8, u8)) -> Color {
+        match self {
+            Self::TrueColor => Color::Rgb {
+                r: rgb.0,
+                g: rgb.1,
+                b: rgb.2,
+            },
+            Self::Ansi256 => Color::AnsiValue(Self::ansi256(rgb)),
+            Self::Ansi16 => ANSI_COLORS
+                .iter()
+                .min_by_key(|(_, _, ansi)| distance(*ansi, rgb))
+                .map_or(Color::Reset, |(_, color, _)| *color),
+        }
+    }
+
+    fn ansi256((r, g, b): (u8, u8, u8)) -> u8 {
+        let level = |value: u8| {
+            CUBE_LEVELS
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, level)| level.abs_diff(value))
+                .map_or(0, |(index, _)| index)
+        };
+        let (ri, gi, bi) = (level(r), level(g), level(b));
+        let cube = (CUBE_LEVELS[ri], CUBE_LEVELS[gi], CUBE_LEVELS[bi]);
+        // 232..=255 是从 8 到 238 的灰阶, 灰色用它更接近
+        let average = (u16::from(r) + u16::from(g) + u16::from(b)) / 3;
+        let grey_index = u8::try_from((average.saturating_sub(8) / 10).min(23)).unwrap_or(0);
+        let grey = grey_index.saturating_mul(10).saturating_add(8);
+        if distance((grey, grey, grey), (r, g, b)) < distance(cube, (r, g, b)) {
+            232_u8.saturating_add(grey_index)
+        } else {
+            let index = ri
+                .saturating_mul(36)
+                .saturating_add(gi.saturating_mul(6))
+                .saturating_add(bi);
+            16_u8.saturating_add(u8::try_from(index).unwrap_or(0))
+        }
+    }
+}
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let square = |x: u8, y: u8| u32::from(x.abs_diff(y)).pow(2);
+    square(a.0, b.0) + square(a.1, b.1) + square(a.2, b.2)
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Theme {
+    pub text: ContentStyle,
+    pub status_bar: ContentStyle,
+    pub message_bar: ContentStyle,
+    gutter: ContentStyle,
+    selection: ContentStyle,
+    search_match: ContentStyle,
+    keyword: ContentStyle,
+    types: ContentStyle,
+    string: ContentStyle,
+    comment: ContentStyle,
+    number: ContentStyle,
+}
+
+impl Theme {
+    pub fn load(name: &str) -> Result<Self, String> {
+        let support = ColorSupport::detect();
+        let mut theme = Self::default();
+        if let Some((_, text)) = BUILTIN_THEMES
+            .iter()
+            .find(|(builtin, _)| *builtin == DEFAULT_THEME)
+        {
+            theme.apply(text, support)?;
+        }
+        // 用户目录下的同名主题优先于内置主题
+        let path = paths::config_dir().map(|dir| dir.join("themes").join(format!("{name}.theme")));
+        let text = match path.and_then(|path| fs::read_to_string(path).ok()) {
+            Some(text) => text,
+            None => BUILTIN_THEMES
+                .iter()
+                .find(|(builtin, _)| *builtin == name)
+                .map(|(_, text)| (*text).to_string())
+                .ok_or_else(|| format!("theme '{name}' not found"))?,
+        };
+        theme.apply(&text, support)?;
+        Ok(theme)
+    }
+
+    fn apply(&mut self, text: &str, support: ColorSupport) -> Result<(), String> {
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let number = index.saturating_add(1);
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("line {number}: expected 'key = style'"))?;
+            let style =
+                parse_style(value, support).map_err(|err| format!("line {number}: {err}"))?;
+            let slot = match key.trim() {
+                "text" => &mut self.text,
+                "status_bar" => &mut self.status_bar,
+                "message_bar" => &mut self.message_bar,
+                "gutter" => &mut self.gutter,
+                "selection" => &mut self.selection,
+                "search_match" => &mut self.search_match,
+                "keyword" => &mut self.keyword,
+                "type" => &mut self.types,
+                "string" => &mut self.string,
+                "comment" => &mut self.comment,
+                "number" => &mut self.number,
+                key => return Err(format!("line {number}: unknown key '{key}'")),
+            };
+            *slot = style;
+        }
+        Ok(())
+    }
+
+    pub fn token(&self, token: Option<TokenType>) -> ContentStyle {
+        let style = match token {
+            None => return self.text,
+            Some(TokenType::Keyword) => self.keyword,
+            Some(TokenType::Type) => self.types,
+            Some(TokenType::String) => self.string,
+            Some(TokenType::Comment) => self.comment,
+            Some(TokenType::Number) => self.number,
+            Some(TokenType::SearchMatch) => self.search_match,
+            Some(TokenType::Selection) => self.selection,
+        };
+        layered(self.text, style)
+    }
+
+    pub fn gutter(&self) -> ContentStyle {
+        layered(self.text, self.gutter)
+    }
+
+    pub fn selection(&self) -> ContentStyle {
+        layered(self.text, self.selection)
+    }
+}
+
+// 上层没有设置的颜色沿用下层的, 这样语法高亮不会盖掉正文背景色
+fn layered(base: ContentStyle, top: ContentStyle) -> ContentStyle {
+    let mut attributes = base.attributes;
+    attributes.extend(top.attributes);
+    ContentStyle {
+        foreground_color: top.foreground_color.or(base.foreground_color),
+        background_color: top.background_color.or(base.background_color),
+        underline_color: top.underline_color.or(base.underline_color),
+        attributes,
+    }
+}
+
+fn parse_style(value: &str, support: ColorSupport) -> Result<ContentStyle, String> {
+    let mut style = ContentStyle::new();
+    let mut words = value.split_whitespace();
+    while let Some(word) = words.next() {
+        let attribute = match word {
+            "bold" => Some(Attribute::Bold),
+            "dim" => Some(Attribute::Dim),
+            "italic" => Some(Attribute::Italic),
+            "underline" => Some(Attribute::Underlined),
+            "reverse" => Some(Attribute::Reverse),
+            _ => None,
+        };
+        if let Some(attribute) = attribute {
+            style.attributes.set(attribute);
+        } else if word == "on" {
+            let color = words
+                .next()
+                .ok_or_else(|| String::from("expected a color after 'on'"))?;
+            style.background_color = parse_color(color, support)?;
+        } else {
+            style.foreground_color = parse_color(word, support)?;
+        }
+    }
+    Ok(style)
+}
+
+fn parse_color(value: &str, support: ColorSupport) -> Result<Option<Color>, String> {
+    if let Some(hex) = value.strip_prefix('#') {
+        let channel = |range: std::ops::Range<usize>| {
+            hex.get(range)
+                .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+        };
+        return match (hex.len(), channel(0..2), channel(2..4), channel(4..6)) {
+            (6, Some(r), Some(g), Some(b)) => Ok(Some(support.color((r, g, b)))),
+            _ => Err(format!("invalid color '{value}'")),
+        };
+    }
+    let name: String = value
+        .chars()
+        .filter(|ch| *ch != '_' && *ch != '-')
+        .collect::<String>()
+        .to_ascii_lowercase();
+    if matches!(name.as_str(), "default" | "reset" | "none") {
+        return Ok(None);
+    }
+    ANSI_COLORS
+        .iter()
+        .find(|(ansi, _, _)| *ansi == name)
+        .map(|(_, color, _)| Some(*color))
+        .ok_or_else(|| format!("invalid color '{value}'"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ansi256_cube_and_grey() {
+        assert_eq!(ColorSupport::ansi256((0, 0, 0)), 16);
+        assert_eq!(ColorSupport::ansi256((255, 0, 0)), 196);
+        assert_eq!(ColorSupport::ansi256((255, 255, 255)), 231);
+        assert_eq!(ColorSupport::ansi256((0x61, 0xaf, 0xef)), 75);
+        // 灰色落在灰阶上
+        assert_eq!(ColorSupport::ansi256((128, 128, 128)), 244);
+        assert_eq!(ColorSupport::ansi256((0x28, 0x2c, 0x34)), 235);
+    }
+
+    #[test]
+    fn nearest_color_fallback() {
+        let color = |rgb| ColorSupport::Ansi16.color(rgb);
+        assert_eq!(color((250, 10, 10)), Color::Red);
+        assert_eq!(color((120, 120, 120)), Color::DarkGrey);
+        assert_eq!(color((0, 0, 230)), Color::DarkBlue);
+        assert_eq!(
+            ColorSupport::Ansi256.color((255, 0, 0)),
+            Color::AnsiValue(196)
+        );
+        assert_eq!(
+            ColorSupport::TrueColor.color((1, 2, 3)),
+            Color::Rgb { r: 1, g: 2, b: 3 }
+        );
+    }
+
+    #[test]
+    fn parse_styles() {
+        let style = parse_style("#282c34 on #61afef bold", ColorSupport::TrueColor).unwrap();
+        assert_eq!(
+            style.foreground_color,
+            Some(Color::Rgb {
+                r: 0x28,
+                g: 0x2c,
+                b: 0x34
+            })
+        );
+        assert_eq!(
+            style.background_color,
+            Some(Color::Rgb {
+                r: 0x61,
+                g: 0xaf,
+                b: 0xef
+            })
+        );
+        assert!(style.attributes.has(Attribute::Bold));
+        let style = parse_style("Dark_Grey on default italic", ColorSupport::Ansi16).unwrap();
+        assert_eq!(style.foreground_color, Some(Color::DarkGrey));
+        assert_eq!(style.background_color, None);
+        assert!(style.attributes.has(Attribute::Italic));
+        let style = parse_style("#61afef", ColorSupport::Ansi16).unwrap();
+        assert_eq!(style.foreground_color, Some(Color::Blue));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let support = ColorSupport::TrueColor;
+        assert!(parse_style("red on", support).is_err());
+        assert!(parse_style("#12345", support).is_err());
+        assert!(parse_style("#12345g", support).is_err());
+        assert!(parse_style("purple", support).is_err());
+    }
+
+    #[test]
+    fn builtin_themes_parse() {
+        for (name, text) in BUILTIN_THEMES {
+            let mut theme = Theme::default();
+            assert!(theme.apply(text, ColorSupport::Ansi16).is_ok(), "{name}");
+        }
+    }
+}
@@ -0,0 +1,1447 @@
+use crate::{
+    command::{Edit, Move},
+    keymap::Key,
+    killring::KillRing,
+    line::Line,
+    view::{Location, View},
+};
+
+const MAX_COUNT: usize = 9999;
+const BACKSPACE: char = '\u{8}';
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Mode {
+    #[default]
+    Normal,
+    Insert,
+    Visual,
+    VisualLine,
+}
+
+impl Mode {
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Normal => "NORMAL",
+            Self::Insert => "INSERT",
+            Self::Visual => "VISUAL",
+            Self::VisualLine => "VISUAL LINE",
+        }
+    }
+
+    const fn is_visual(self) -> bool {
+        matches!(self, Self::Visual | Self::VisualLine)
+    }
+}
+
+pub enum Outcome {
+    Handled,
+    Pass,
+    Readonly,
+    // 打开命令行, 附带预先填入的行范围
+    CommandLine(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Delete,
+    Change,
+    Yank,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Motion {
+    Left,
+    Right,
+    Up,
+    Down,
+    NextLine,
+    PreviousLine,
+    WordStart { big: bool },
+    WordEnd { big: bool },
+    WordBack { big: bool },
+    LineStart,
+    FirstNonBlank,
+    LineEnd,
+    FirstLine,
+    LastLine,
+    ParagraphUp,
+    ParagraphDown,
+    Find { ch: char, forward: bool, till: bool },
+}
+
+impl Motion {
+    const fn is_linewise(self) -> bool {
+        matches!(
+            self,
+            Self::Up
+                | Self::Down
+                | Self::NextLine
+                | Self::PreviousLine
+                | Self::FirstLine
+                | Self::LastLine
+        )
+    }
+
+    const fn is_inclusive(self) -> bool {
+        matches!(
+            self,
+            Self::WordEnd { .. } | Self::LineEnd | Self::Find { forward: true, .. }
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Object {
+    Word { big: bool },
+    Quote(char),
+    Pair(char, char),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Target {
+    Motion(Motion),
+    Object { object: Object, inner: bool },
+    Lines,
+    Selection,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InsertAt {
+    Before,
+    After,
+    LineStart,
+    LineEnd,
+    Below,
+    Above,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Move(Motion),
+    Operate(Operator, Target),
+    Insert(InsertAt),
+    Put { before: bool },
+    Replace(char),
+    Join,
+    Visual(Mode),
+    SwapAnchor,
+    Select { object: Object, inner: bool },
+    Repeat,
+}
+
+impl Action {
+    const fn is_change(self) -> bool {
+        !matches!(
+            self,
+            Self::Move(_)
+                | Self::Operate(Operator::Yank, _)
+                | Self::Visual(_)
+                | Self::SwapAnchor
+                | Self::Select { .. }
+        )
+    }
+}
+
+enum Parsed<T> {
+    Incomplete,
+    Invalid,
+    Done(T),
+}
+
+#[derive(Debug, Clone)]
+struct Change {
+    count: Option<usize>,
+    action: Action,
+    text: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Region {
+    start: Location,
+    end: Location,
+    linewise: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Class {
+    Blank,
+    Word,
+    Punctuation,
+}
+
+#[derive(Default)]
+pub struct Vi {
+    mode: Mode,
+    keys: Vec<char>,
+    anchor: Location,
+    last_change: Option<Change>,
+    inserting: Option<Change>,
+}
+
+impl Vi {
+    pub const fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub fn pending(&self) -> String {
+        self.keys.iter().collect()
+    }
+
+    pub fn reset(&mut self, view: &mut View) {
+        self.keys.clear();
+        self.inserting = None;
+        self.mode = Mode::Normal;
+        view.set_selection(None);
+    }
+
+    pub fn handle_key(&mut self, key: Key, view: &mut View, kill_ring: &mut KillRing) -> Outcome {
+        if self.mode == Mode::Insert {
+            if key.is_escape() {
+                self.finish_insert(view);
+                return Outcome::Handled;
+            }
+            self.record(key);
+            return Outcome::Pass;
+        }
+        let ch = if key.is_escape() {
+            // 先取消未完成的命令, 再退出可视模式
+            if self.keys.is_empty() && self.mode.is_visual() {
+                self.set_mode(Mode::Normal, view);
+            }
+            self.keys.clear();
+            return Outcome::Handled;
+        } else if key.is_enter() {
+            '\n'
+        } else if key.is_backspace() {
+            BACKSPACE
+        } else if let Some(ch) = key.as_char() {
+            ch
+        } else {
+            // 方向键和 Ctrl 组合键交给普通键位表
+            self.keys.clear();
+            return Outcome::Pass;
+        };
+        if ch == ':' && self.keys.iter().all(char::is_ascii_digit) {
+            return Outcome::CommandLine(self.command_line_range(view));
+        }
+        self.keys.push(ch);
+        let parsed = if self.mode.is_visual() {
+            parse_visual(&self.keys)
+        } else {
+            parse_normal(&self.keys)
+        };
+        match parsed {
+            Parsed::Incomplete => Outcome::Handled,
+            Parsed::Invalid => {
+                self.keys.clear();
+                Outcome::Handled
+            }
+            Parsed::Done((count, action)) => {
+                self.keys.clear();
+                if action.is_change() && view.is_readonly() {
+                    return Outcome::Readonly;
+                }
+                self.execute(count, action, view, kill_ring);
+                Outcome::Handled
+            }
+        }
+    }
+
+    // 通过键位表执行的命令也可能移动光标, 之后要同步选区和光标位置
+    pub fn sync(&mut self, view: &mut View) {
+        match self.mode {
+            Mode::Normal => {
+                view.set_selection(None);
+                let at = view.text_location();
+                let last = view.line_count().saturating_sub(1);
+                if at.line_index > last {
+                    view.set_text_location(Location {
+                        line_index: last,
+                        ..at
+                    });
+                }
+                view.snap_to_last_grapheme();
+            }
+            Mode::Insert => view.set_selection(None),
+            Mode::Visual | Mode::VisualLine => {
+                let region = self.selection(view);
+                view.set_selection(Some((region.start, region.end)));
+            }
+        }
+    }
+
+    // 可视模式下是选中的行, 否则由计数决定行数
+    fn command_line_range(&mut self, view: &mut View) -> String {
+        let count = split_count(&self.keys).0.unwrap_or(1);
+        self.keys.clear();
+        if self.mode.is_visual() {
+            let cursor = view.text_location().line_index;
+            let (first, last) = (
+                self.anchor.line_index.min(cursor),
+                self.anchor.line_index.max(cursor),
+            );
+            self.set_mode(Mode::Normal, view);
+            format!("{},{}", first.saturating_add(1), last.saturating_add(1))
+        } else if count > 1 {
+            format!(".,.+{}", count.saturating_sub(1))
+        } else {
+            String::new()
+        }
+    }
+
+    fn set_mode(&mut self, mode: Mode, view: &mut View) {
+        if mode.is_visual() && !self.mode.is_visual() {
+            self.anchor = view.text_location();
+        }
+        self.mode = mode;
+        self.sync(view);
+    }
+
+    fn selection(&self, view: &View) -> Region {
+        let cursor = view.text_location();
+        let (start, end) = (self.anchor.min(cursor), self.anchor.max(cursor));
+        if self.mode == Mode::VisualLine {
+            Region {
+                start: line_start(start.line_index),
+                end: line_end(view, end.line_index),
+                linewise: true,
+            }
+        } else {
+            Region {
+                start,
+                end: next_in_line(view, end),
+                linewise: false,
+            }
+        }
+    }
+
+    fn record(&mut self, key: Key) {
+        let Some(change) = &mut self.inserting else {
+            return;
+        };
+        if let Some(ch) = key.as_char() {
+            change.text.push(ch);
+        } else if key.is_enter() {
+            change.text.push('\n');
+        } else if key.is_tab() {
+            change.text.push('\t');
+        } else if key.is_backspace() {
+            change.text.push(BACKSPACE);
+        } else {
+            // 插入时移动过光标的修改无法用 . 重复
+            self.inserting = None;
+        }
+    }
+
+    fn start_insert(&mut self, count: Option<usize>, action: Action) {
+        self.mode = Mode::Insert;
+        self.inserting =
+            (!matches!(action, Action::Operate(_, Target::Selection))).then(|| Change {
+                count,
+                action,
+                text: String::new(),
+            });
+    }
+
+    fn finish_insert(&mut self, view: &mut View) {
+        if let Some(change) = self.inserting.take() {
+            // 3ix<Esc> 插入 xxx
+            if matches!(
+                change.action,
+                Action::Insert(
+                    InsertAt::Before | InsertAt::After | InsertAt::LineStart | InsertAt::LineEnd
+                )
+            ) {
+                for _ in 1..change.count.unwrap_or(1) {
+                    replay(view, &change.text);
+                }
+            } else if matches!(
+                change.action,
+                Action::Insert(InsertAt::Below | InsertAt::Above)
+            ) {
+                // 3oab<Esc> 新开三行 ab
+                for _ in 1..change.count.unwrap_or(1) {
+                    view.handler_edit(Edit::InsertNewline);
+                    replay(view, &change.text);
+                }
+            }
+            self.last_change = Some(change);
+        }
+        self.mode = Mode::Normal;
+        let at = view.text_location();
+        view.set_text_location(Location {
+            grapheme_index: at.grapheme_index.saturating_sub(1),
+            ..at
+        });
+        self.sync(view);
+    }
+
+    fn execute(
+        &mut self,
+        count: Option<usize>,
+        action: Action,
+        view: &mut View,
+        kill_ring: &mut KillRing,
+    ) {
+        let times = count.unwrap_or(1);
+        match action {
+            Action::Move(motion) => move_cursor(view, motion, count),
+            Action::Operate(operator, target) => {
+                let region = if target == Target::Selection {
+                    let region = self.selection(view);
+                    self.mode = Mode::Normal;
+                    Some(region)
+                } else {
+                    target_region(view, operator, target, count)
+                };
+                let Some(region) = region else {
+                    self.sync(view);
+                    return;
+                };
+                operate(view, kill_ring, operator, region);
+                if operator == Operator::Change {
+                    self.start_insert(count, action);
+                }
+            }
+            Action::Insert(at) => {
+                open_insert(view, at);
+                self.start_insert(count, action);
+            }
+            Action::Put { before } => put(view, kill_ring, before, times),
+            Action::Replace(ch) => replace(view, ch, times),
+            Action::Join => join(view, times.max(2)),
+            Action::Visual(mode) if self.mode == mode => self.set_mode(Mode::Normal, view),
+            Action::Visual(mode) => self.set_mode(mode, view),
+            Action::SwapAnchor => {
+                let cursor = view.text_location();
+                view.set_text_location(self.anchor);
+                self.anchor = cursor;
+            }
+            Action::Select { object, inner } => {
+                if let Some(region) = object_region(view, view.text_location(), object, inner) {
+                    if region.linewise {
+                        self.mode = Mode::VisualLine;
+                    }
+                    self.anchor = region.start;
+                    view.set_text_location(
+                        previous_location(view, region.end).unwrap_or(region.end),
+                    );
+                }
+            }
+            Action::Repeat => self.repeat(count, view, kill_ring),
+        }
+        if action.is_change()
+            && self.mode == Mode::Normal
+            && !matches!(
+                action,
+                Action::Repeat | Action::Operate(_, Target::Selection)
+            )
+        {
+            self.last_change = Some(Change {
+                count,
+                action,
+                text: String::new(),
+            });
+        }
+        self.sync(view);
+    }
+
+    fn repeat(&mut self, count: Option<usize>, view: &mut View, kill_ring: &mut KillRing) {
+        let Some(change) = self.last_change.clone() else {
+            return;
+        };
+        let count = count.or(change.count);
+        self.execute(count, change.action, view, kill_ring);
+        if self.mode == Mode::Insert {
+            replay(view, &change.text);
+            if let Some(inserting) = &mut self.inserting {
+                inserting.text = change.text;
+            }
+            self.finish_insert(view);
+        }
+    }
+}
+
+fn replay(view: &mut View, text: &str) {
+    for ch in text.chars() {
+        let edit = match ch {
+            '\n' => Edit::InsertNewline,
+            BACKSPACE => Edit::DeleteBackward,
+            ch => Edit::Insert(ch),
+        };
+        view.handler_edit(edit);
+    }
+}
+
+fn parse_normal(keys: &[char]) -> Parsed<(Option<usize>, Action)> {
+    let (count, rest) = split_count(keys);
+    let action = match rest {
+        [] | ['r'] => return Parsed::Incomplete,
+        ['.'] => Action::Repeat,
+        ['v'] => Action::Visual(Mode::Visual),
+        ['V'] => Action::Visual(Mode::VisualLine),
+        ['i'] => Action::Insert(InsertAt::Before),
+        ['a'] => Action::Insert(InsertAt::After),
+        ['I'] => Action::Insert(InsertAt::LineStart),
+        ['A'] => Action::Insert(InsertAt::LineEnd),
+        ['o'] => Action::Insert(InsertAt::Below),
+        ['O'] => Action::Insert(InsertAt::Above),
+        ['x'] => Action::Operate(Operator::Delete, Target::Motion(Motion::Right)),
+        ['X'] => Action::Operate(Operator::Delete, Target::Motion(Motion::Left)),
+        ['D'] => Action::Operate(Operator::Delete, Target::Motion(Motion::LineEnd)),
+        ['C'] => Action::Operate(Operator::Change, Target::Motion(Motion::LineEnd)),
+        ['s'] => Action::Operate(Operator::Change, Target::Motion(Motion::Right)),
+        ['S'] => Action::Operate(Operator::Change, Target::Lines),
+        ['Y'] => Action::Operate(Operator::Yank, Target::Lines),
+        ['p'] => Action::Put { before: false },
+        ['P'] => Action::Put { before: true },
+        ['J'] => Action::Join,
+        ['r', BACKSPACE] => return Parsed::Invalid,
+        ['r', ch] => Action::Replace(*ch),
+        [op @ ('d' | 'c' | 'y'), rest @ ..] => {
+            let operator = match op {
+                'd' => Operator::Delete,
+                'c' => Operator::Change,
+                _ => Operator::Yank,
+            };
+            // 2d3w 等于 d6w
+            let (inner_count, rest) = split_count(rest);
+            let target = match rest {
+                [] | ['i' | 'a'] => return Parsed::Incomplete,
+                [ch] if ch == op => Target::Lines,
+                [kind @ ('i' | 'a'), object] => match parse_object(*object) {
+                    Some(object) => Target::Object {
+                        object,
+                        inner: *kind == 'i',
+                    },
+                    None => return Parsed::Invalid,
+                },
+                motion => match parse_motion(motion) {
+                    Parsed::Done(motion) => Target::Motion(motion),
+                    Parsed::Incomplete => return Parsed::Incomplete,
+                    Parsed::Invalid => return Parsed::Invalid,
+                },
+            };
+            let count = match (count, inner_count) {
+                (None, None) => None,
+                (outer, inner) => Some(
+                    outer
+                        .unwrap_or(1)
+                        .saturating_mul(inner.unwrap_or(1))
+                        .min(MAX_COUNT),
+                ),
+            };
+            return Parsed::Done((count, Action::Operate(operator, target)));
+        }
+        motion => match parse_motion(motion) {
+            Parsed::Done(motion) => Action::Move(motion),
+            Parsed::Incomplete => return Parsed::Incomplete,
+            Parsed::Invalid => return Parsed::Invalid,
+        },
+    };
+    Parsed::Done((count, action))
+}
+
+fn parse_visual(keys: &[char]) -> Parsed<(Option<usize>, Action)> {
+    let (count, rest) = split_count(keys);
+    let action = match rest {
+        [] | ['i' | 'a'] => return Parsed::Incomplete,
+        ['v'] => Action::Visual(Mode::Visual),
+        ['V'] => Action::Visual(Mode::VisualLine),
+        ['o'] => Action::SwapAnchor,
+        ['d' | 'x' | 'D' | 'X'] => Action::Operate(Operator::Delete, Target::Selection),
+        ['c' | 's' | 'C' | 'S'] => Action::Operate(Operator::Change, Target::Selection),
+        ['y' | 'Y'] => Action::Operate(Operator::Yank, Target::Selection),
+        [kind @ ('i' | 'a'), object] => match parse_object(*object) {
+            Some(object) => Action::Select {
+                object,
+                inner: *kind == 'i',
+            },
+            None => return Parsed::Invalid,
+        },
+        motion => match parse_motion(motion) {
+            Parsed::Done(motion) => Action::Move(motion),
+            Parsed::Incomplete => return Parsed::Incomplete,
+            Parsed::Invalid => return Parsed::Invalid,
+        },
+    };
+    Parsed::Done((count, action))
+}
+
+// 开头的 0 是移动到行首, 不是计数
+fn split_count(keys: &[char]) -> (Option<usize>, &[char]) {
+    let digits = keys
+        .iter()
+        .enumerate()
+        .take_while(|(index, ch)| ch.is_ascii_digit() && (*index > 0 || **ch != '0'))
+        .count();
+    let (count, rest) = keys.split_at(digits);
+    let count = count
+        .iter()
+        .collect::<String>()
+        .parse::<usize>()
+        .ok()
+        .map(|count| count.min(MAX_COUNT));
+    (count, rest)
+}
+
+fn parse_motion(keys: &[char]) -> Parsed<Motion> {
+    let motion = match keys {
+        [] | ['g'] | ['f' | 'F' | 't' | 'T'] => return Parsed::Incomplete,
+        ['h' | BACKSPACE] => Motion::Left,
+        ['l' | ' '] => Motion::Right,
+        ['j'] => Motion::Down,
+        ['k'] => Motion::Up,
+        ['+' | '\n'] => Motion::NextLine,
+        ['-'] => Motion::PreviousLine,
+        ['w'] => Motion::WordStart { big: false },
+        ['W'] => Motion::WordStart { big: true },
+        ['e'] => Motion::WordEnd { big: false },
+        ['E'] => Motion::WordEnd { big: true },
+        ['b'] => Motion::WordBack { big: false },
+        ['B'] => Motion::WordBack { big: true },
+        ['0'] => Motion::LineStart,
+        ['^'] => Motion::FirstNonBlank,
+        ['$'] => Motion::LineEnd,
+        ['g', 'g'] => Motion::FirstLine,
+        ['G'] => Motion::LastLine,
+        ['{'] => Motion::ParagraphUp,
+        ['}'] => Motion::ParagraphDown,
+        [kind @ ('f' | 'F' | 't' | 'T'), ch] => Motion::Find {
+            ch: *ch,
+            forward: kind.is_ascii_lowercase(),
+            till: matches!(kind, 't' | 'T'),
+        },
+        _ => return Parsed::Invalid,
+    };
+    Parsed::Done(motion)
+}
+
+fn parse_object(ch: char) -> Option<Object> {
+    match ch {
+        'w' => Some(Object::Word { big: false }),
+        'W' => Some(Object::Word { big: true }),
+        '"' | '\'' | '`' => Some(Object::Quote(ch)),
+        '(' | ')' | 'b' => Some(Object::Pair('(', ')')),
+        '{' | '}' | 'B' => Some(Object::Pair('{', '}')),
+        '[' | ']' => Some(Object::Pair('[', ']')),
+        '<' | '>' => Some(Object::Pair('<', '>')),
+        _ => None,
+    }
+}
+
+fn line_len(view: &View, line_index: usize) -> usize {
+    view.line(line_index).map_or(0, Line::len)
+}
+
+const fn line_start(line_index: usize) -> Location {
+    Location {
+        line_index,
+        grapheme_index: 0,
+    }
+}
+
+fn line_end(view: &View, line_index: usize) -> Location {
+    Location {
+        line_index,
+        grapheme_index: line_len(view, line_index),
+    }
+}
+
+fn first_non_blank(view: &View, line_index: usize) -> Location {
+    Location {
+        line_index,
+        grapheme_index: view.line(line_index).map_or(0, Line::indent_end),
+    }
+}
+
+fn char_at(view: &View, at: Location) -> Option<char> {
+    view.line(at.line_index)?.char_at(at.grapheme_index)
+}
+
+fn next_in_line(view: &View, at: Location) -> Location {
+    Location {
+        grapheme_index: at
+            .grapheme_index
+            .saturating_add(1)
+            .min(line_len(view, at.line_index)),
+        ..at
+    }
+}
+
+// 行尾也算一个位置, 相当于换行符
+fn next_location(view: &View, at: Location) -> Option<Location> {
+    if at.grapheme_index < line_len(view, at.line_index) {
+        Some(Location {
+            grapheme_index: at.grapheme_index.saturating_add(1),
+            ..at
+        })
+    } else if at.line_index.saturating_add(1) < view.line_count() {
+        Some(line_start(at.line_index.saturating_add(1)))
+    } else {
+        None
+    }
+}
+
+fn previous_location(view: &View, at: Location) -> Option<Location> {
+    if at.grapheme_index > 0 {
+        Some(Location {
+            grapheme_index: at.grapheme_index.saturating_sub(1),
+            ..at
+        })
+    } else if at.line_index > 0 {
+        Some(line_end(view, at.line_index.saturating_sub(1)))
+    } else {
+        None
+    }
+}
+
+fn class_at(view: &View, at: Location, big: bool) -> Class {
+    match char_at(view, at) {
+        None => Class::Blank,
+        Some(ch) if ch.is_whitespace() => Class::Blank,
+        Some(_) if big => Class::Word,
+        Some(ch) if ch.is_alphanumeric() || ch == '_' => Class::Word,
+        Some(_) => Class::Punctuation,
+    }
+}
+
+fn is_empty_line(view: &View, line_index: usize) -> bool {
+    line_len(view, line_index) == 0
+}
+
+fn word_start(view: &View, from: Location, big: bool) -> Location {
+    let class = class_at(view, from, big);
+    let mut at = from;
+    while class != Class::Blank && class_at(view, at, big) == class {
+        match next_location(view, at) {
+            Some(next) => at = next,
+            None => return at,
+        }
+    }
+    // 空行也算一个单词
+    while class_at(view, at, big) == Class::Blank
+        && !(at != from && at.grapheme_index == 0 && is_empty_line(view, at.line_index))
+    {
+        match next_location(view, at) {
+            Some(next) => at = next,
+            None => return at,
+        }
+    }
+    at
+}
+
+fn word_end(view: &View, from: Location, big: bool) -> Location {
+    let mut at = from;
+    loop {
+        match next_location(view, at) {
+            Some(next) => at = next,
+            None => return at,
+        }
+        if class_at(view, at, big) != Class::Blank {
+            break;
+        }
+    }
+    let class = class_at(view, at, big);
+    while let Some(next) = next_location(view, at) {
+        if class_at(view, next, big) != class {
+            break;
+        }
+        at = next;
+    }
+    at
+}
+
+fn word_back(view: &View, from: Location, big: bool) -> Location {
+    let mut at = from;
+    loop {
+        match previous_location(view, at) {
+            Some(previous) => at = previous,
+            None => return at,
+        }
+        if class_at(view, at, big) != Class::Blank || is_empty_line(view, at.line_index) {
+            break;
+        }
+    }
+    let class = class_at(view, at, big);
+    if class == Class::Blank {
+        return at;
+    }
+    while let Some(previous) = previous_location(view, at) {
+        if class_at(view, previous, big) != class {
+            break;
+        }
+        at = previous;
+    }
+    at
+}
+
+fn paragraph(view: &View, from: Location, times: usize, up: bool) -> Location {
+    let is_blank = |index: usize| view.line(index).is_none_or(Line::is_blank);
+    let last = view.line_count().saturating_sub(1);
+    let mut line_index = from.line_index;
+    for _ in 0..times {
+        if up {
+            line_index = line_index.saturating_sub(1);
+            while line_index > 0 && is_blank(line_index) {
+                line_index = line_index.saturating_sub(1);
+            }
+            while line_index > 0 && !is_blank(line_index) {
+                line_index = line_index.saturating_sub(1);
+            }
+        } else {
+            line_index = line_index.saturating_add(1).min(last);
+            while line_index < last && is_blank(line_index) {
+                line_index = line_index.saturating_add(1);
+            }
+            while line_index < last && !is_blank(line_index) {
+                line_index = line_index.saturating_add(1);
+            }
+        }
+    }
+    if !up && !is_blank(line_index) {
+        line_end(view, line_index)
+    } else {
+        line_start(line_index)
+    }
+}
+
+fn find_in_line(
+    view: &View,
+    from: Location,
+    (ch, forward, till): (char, bool, bool),
+    times: usize,
+) -> Option<Location> {
+    let line = view.line(from.line_index)?;
+    let mut found = from.grapheme_index;
+    for _ in 0..times {
+        found = if forward {
+            (found.saturating_add(1)..line.len()).find(|index| line.char_at(*index) == Some(ch))?
+        } else {
+            (0..found)
+                .rev()
+                .find(|index| line.char_at(*index) == Some(ch))?
+        };
+    }
+    let grapheme_index = match (till, forward) {
+        (false, _) => found,
+        (true, true) => found.saturating_sub(1),
+        (true, false) => found.saturating_add(1),
+    };
+    // 紧挨着目标字符时 t/T 不移动，dtx 什么也不删
+    if till && grapheme_index == from.grapheme_index {
+        return None;
+    }
+    Some(Location {
+        grapheme_index,
+        ..from
+    })
+}
+
+fn motion_target(
+    view: &View,
+    from: Location,
+    motion: Motion,
+    count: Option<usize>,
+) -> Option<Location> {
+    let times = count.unwrap_or(1);
+    let last = view.line_count().saturating_sub(1);
+    let repeat = |step: fn(&View, Location, bool) -> Location, big: bool| {
+        (0..times).fold(from, |at, _| step(view, at, big))
+    };
+    let target = match motion {
+        Motion::Left => Location {
+            grapheme_index: from.grapheme_index.saturating_sub(times),
+            ..from
+        },
+        Motion::Right => Location {
+            grapheme_index: from
+                .grapheme_index
+                .saturating_add(times)
+                .min(line_len(view, from.line_index)),
+            ..from
+        },
+        Motion::Up => Location {
+            line_index: from.line_index.saturating_sub(times),
+            ..from
+        },
+        Motion::Down => Location {
+            line_index: from.line_index.saturating_add(times).min(last),
+            ..from
+        },
+        Motion::NextLine => first_non_blank(view, from.line_index.saturating_add(times).min(last)),
+        Motion::PreviousLine => first_non_blank(view, from.line_index.saturating_sub(times)),
+        Motion::WordStart { big } => repeat(word_start, big),
+        Motion::WordEnd { big } => repeat(word_end, big),
+        Motion::WordBack { big } => repeat(word_back, big),
+        Motion::LineStart => line_start(from.line_index),
+        Motion::FirstNonBlank => first_non_blank(view, from.line_index),
+        Motion::LineEnd => {
+            let line_index = from
+                .line_index
+                .saturating_add(times.saturating_sub(1))
+                .min(last);
+            Location {
+                line_index,
+                grapheme_index: line_len(view, line_index).saturating_sub(1),
+            }
+        }
+        Motion::FirstLine => {
+            first_non_blank(view, count.map_or(0, |n| n.saturating_sub(1)).min(last))
+        }
+        Motion::LastLine => {
+            first_non_blank(view, count.map_or(last, |n| n.saturating_sub(1)).min(last))
+        }
+        Motion::ParagraphUp => paragraph(view, from, times, true),
+        Motion::ParagraphDown => paragraph(view, from, times, false),
+        Motion::Find { ch, forward, till } => {
+            return find_in_line(view, from, (ch, forward, till), times)
+        }
+    };
+    Some(target)
+}
+
+fn move_cursor(view: &mut View, motion: Motion, count: Option<usize>) {
+    let times = count.unwrap_or(1);
+    let at = view.text_location();
+    // 上下移动交给 View, 这样能保持期望的列
+    match motion {
+        Motion::Up => {
+            for _ in 0..times.min(at.line_index) {
+                view.move_text_location(Move::Up);
+            }
+        }
+        Motion::Down => {
+            let below = view
+                .line_count()
+                .saturating_sub(1)
+                .saturating_sub(at.line_index);
+            for _ in 0..times.min(below) {
+                view.move_text_location(Move::Down);
+            }
+        }
+        _ => {
+            if let Some(target) = motion_target(view, at, motion, count) {
+                view.set_text_location(target);
+            }
+        }
+    }
+}
+
+fn target_region(
+    view: &View,
+    operator: Operator,
+    target: Target,
+    count: Option<usize>,
+) -> Option<Region> {
+    let origin = view.text_location();
+    match target {
+        Target::Motion(motion) => motion_region(view, origin, operator, motion, count),
+        Target::Object { object, inner } => object_region(view, origin, object, inner),
+        Target::Lines => {
+            let last = origin
+                .line_index
+                .saturating_add(count.unwrap_or(1).saturating_sub(1))
+                .min(view.line_count().saturating_sub(1));
+            Some(Region {
+                start: line_start(origin.line_index),
+                end: line_start(last),
+                linewise: true,
+            })
+        }
+        Target::Selection => None,
+    }
+}
+
+fn motion_region(
+    view: &View,
+    origin: Location,
+    operator: Operator,
+    motion: Motion,
+    count: Option<usize>,
+) -> Option<Region> {
+    if let Motion::WordStart { big } = motion {
+        // cw 在单词上时只改到词尾, 不包括后面的空白
+        if operator == Operator::Change && class_at(view, origin, big) != Class::Blank {
+            let same_word = next_location(view, origin)
+                .is_some_and(|next| class_at(view, next, big) == class_at(view, origin, big));
+            let mut end = if same_word {
+                word_end(view, origin, big)
+            } else {
+                origin
+            };
+            for _ in 1..count.unwrap_or(1) {
+                end = word_end(view, end, big);
+            }
+            return Some(Region {
+                start: origin,
+                end: next_in_line(view, end),
+                linewise: false,
+            });
+        }
+    }
+    let target = motion_target(view, origin, motion, count)?;
+    let (start, mut end) = (origin.min(target), origin.max(target));
+    if motion.is_linewise() {
+        return Some(Region {
+            start,
+            end,
+            linewise: true,
+        });
+    }
+    if motion.is_inclusive() {
+        end = next_in_line(view, end);
+    } else if matches!(motion, Motion::WordStart { .. }) && end.line_index > start.line_index {
+        // dw 删除行尾最后一个单词时不删换行
+        end = line_end(view, end.line_index.saturating_sub(1)).max(start);
+    }
+    Some(Region {
+        start,
+        end,
+        linewise: false,
+    })
+}
+
+fn object_region(view: &View, at: Location, object: Object, inner: bool) -> Option<Region> {
+    match object {
+        Object::Word { big } => word_object(view, at, big, inner),
+        Object::Quote(quote) => quote_object(view, at, quote, inner),
+        Object::Pair(open, close) => pair_object(view, at, (open, close), inner),
+    }
+}
+
+fn word_object(view: &View, at: Location, big: bool, inner: bool) -> Option<Region> {
+    let len = line_len(view, at.line_index);
+    if len == 0 {
+        return None;
+    }
+    let class_of = |grapheme_index: usize| {
+        class_at(
+            view,
+            Location {
+                grapheme_index,
+                ..at
+            },
+            big,
+        )
+    };
+    let current = at.grapheme_index.min(len.saturating_sub(1));
+    let class = class_of(current);
+    let mut start = current;
+    while start > 0 && class_of(start.saturating_sub(1)) == class {
+        start = start.saturating_sub(1);
+    }
+    let mut end = current.saturating_add(1);
+    while end < len && class_of(end) == class {
+        end = end.saturating_add(1);
+    }
+    if !inner {
+        // aw 带上后面的空白, 没有时带上前面的空白
+        let next_class = if class == Class::Blank {
+            class_of(end)
+        } else {
+            Class::Blank
+        };
+        if end < len && class_of(end) == next_class {
+            while end < len && class_of(end) == next_class {
+                end = end.saturating_add(1);
+            }
+        } else {
+            while start > 0 && class_of(start.saturating_sub(1)) == Class::Blank {
+                start = start.saturating_sub(1);
+            }
+        }
+    }
+    Some(Region {
+        start: Location {
+            grapheme_index: start,
+            ..at
+        },
+        end: Location {
+            grapheme_index: end,
+            ..at
+        },
+        linewise: false,
+    })
+}
+
+fn quote_object(view: &View, at: Location, quote: char, inner: bool) -> Option<Region> {
+    let line = view.line(at.line_index)?;
+    let quotes: Vec<usize> = (0..line.len())
+        .filter(|index| {
+            line.char_at(*index) == Some(quote)
+                && (*index == 0 || line.char_at(index.saturating_sub(1)) != Some('\\'))
+        })
+        .collect();
+    let (open, close) = quotes
+        .chunks_exact(2)
+        .map(|pair| (pair[0], pair[1]))
+        .find(|(_, close)| at.grapheme_index <= *close)?;
+    let is_blank = |index: usize| line.char_at(index).is_some_and(char::is_whitespace);
+    let (mut start, mut end) = if inner {
+        (open.saturating_add(1), close)
+    } else {
+        (open, close.saturating_add(1))
+    };
+    if !inner {
+        // a" 带上后面的空白, 后面没有时带上前面的空白
+        let trailing = end;
+        while is_blank(end) {
+            end = end.saturating_add(1);
+        }
+        if end == trailing {
+            while start > 0 && is_blank(start.saturating_sub(1)) {
+                start = start.saturating_sub(1);
+            }
+        }
+    }
+    Some(Region {
+        start: Location {
+            grapheme_index: start,
+            ..at
+        },
+        end: Location {
+            grapheme_index: end,
+            ..at
+        },
+        linewise: false,
+    })
+}
+
+fn pair_object(
+    view: &View,
+    at: Location,
+    (open, close): (char, char),
+    inner: bool,
+) -> Option<Region> {
+    // 光标在括号上时就用这一对括号
+    let mut depth: usize = 0;
+    let mut start = at;
+    loop {
+        match char_at(view, start) {
+            Some(ch) if ch == close && start != at => depth = depth.saturating_add(1),
+            Some(ch) if ch == open => {
+                if depth == 0 {
+                    break;
+                }
+                depth = depth.saturating_sub(1);
+            }
+            _ => {}
+        }
+        start = previous_location(view, start)?;
+    }
+    let mut end = next_location(view, start)?;
+    loop {
+        match char_at(view, end) {
+            Some(ch) if ch == open => depth = depth.saturating_add(1),
+            Some(ch) if ch == close => {
+                if depth == 0 {
+                    break;
+                }
+                depth = depth.saturating_sub(1);
+            }
+            _ => {}
+        }
+        end = next_location(view, end)?;
+    }
+    if !inner {
+        return Some(Region {
+            start,
+            end: next_in_line(view, end),
+            linewise: false,
+        });
+    }
+    let from = next_location(view, start)?;
+    // 括号各占一行时, 内部按整行处理
+    let at_line_end = from.grapheme_index == line_len(view, from.line_index);
+    let closer_first = first_non_blank(view, end.line_index) == end;
+    if at_line_end && closer_first && end.line_index > from.line_index {
+        if end.line_index == from.line_index.saturating_add(1) {
+            return None;
+        }
+        return Some(Region {
+            start: line_start(from.line_index.saturating_add(1)),
+            end: line_start(end.line_index.saturating_sub(1)),
+            linewise: true,
+        });
+    }
+    Some(Region {
+        start: from,
+        end,
+        linewise: false,
+    })
+}
+
+fn operate(view: &mut View, kill_ring: &mut KillRing, operator: Operator, region: Region) {
+    if view.line_count() == 0 {
+        return;
+    }
+    let Region {
+        start,
+        end,
+        linewise,
+    } = region;
+    if !linewise {
+        match operator {
+            Operator::Yank => {
+                kill_ring.push(view.text_range(start, end));
+                view.set_text_location(start);
+            }
+            Operator::Delete | Operator::Change => {
+                let killed = view.delete_range(start, end);
+                kill_ring.push(killed);
+            }
+        }
+        return;
+    }
+    let (first, last) = (start.line_index, end.line_index);
+    // 整行文本以换行结尾, 粘贴时据此判断是否按行粘贴
+    kill_ring.push(format!(
+        "{}\n",
+        view.text_range(line_start(first), line_end(view, last))
+    ));
+    match operator {
+        Operator::Yank => {
+            let at = view.text_location();
+            if first < at.line_index {
+                view.set_text_location(Location {
+                    line_index: first,
+                    ..at
+                });
+            }
+        }
+        Operator::Delete => {
+            view.delete_lines(first, last);
+            let line_index = view.text_location().line_index;
+            view.set_text_location(first_non_blank(view, line_index));
+        }
+        Operator::Change => {
+            view.delete_range(first_non_blank(view, first), line_end(view, last));
+        }
+    }
+}
+
+fn open_insert(view: &mut View, at: InsertAt) {
+    let location = view.text_location();
+    match at {
+        InsertAt::Before => {}
+        InsertAt::After => view.set_text_location(next_in_line(view, location)),
+        InsertAt::LineStart => {
+            view.set_text_location(first_non_blank(view, location.line_index));
+        }
+        InsertAt::LineEnd => view.set_text_location(line_end(view, location.line_index)),
+        InsertAt::Below => {
+            view.set_text_location(line_end(view, location.line_index));
+            view.handler_edit(Edit::InsertNewline);
+        }
+        InsertAt::Above => {
+            let indent = view
+                .line(location.line_index)
+                .map_or_else(String::new, |line| line.slice(0..line.indent_end()));
+            view.set_text_location(line_start(location.line_index));
+            view.insert_text("\n");
+            view.set_text_location(line_start(location.line_index));
+            view.insert_text(&indent);
+        }
+    }
+}
+
+fn put(view: &mut View, kill_ring: &KillRing, before: bool, times: usize) {
+    let Some(text) = kill_ring.latest().map(|text| text.repeat(times)) else {
+        return;
+    };
+    let at = view.text_location();
+    if let Some(lines) = text.strip_suffix('\n') {
+        if before {
+            view.set_text_location(line_start(at.line_index));
+            view.insert_text(&text);
+            view.set_text_location(first_non_blank(view, at.line_index));
+        } else {
+            view.set_text_location(line_end(view, at.line_index));
+            view.insert_text(&format!("\n{lines}"));
+            view.set_text_location(first_non_blank(view, at.line_index.saturating_add(1)));
+        }
+        return;
+    }
+    let start = if before { at } else { next_in_line(view, at) };
+    view.set_text_location(start);
+    view.insert_text(&text);
+    // 光标停在粘贴内容的最后一个字符上
+    let end = view.text_location();
+    view.set_text_location(Location {
+        grapheme_index: end.grapheme_index.saturating_sub(1),
+        ..end
+    });
+}
+
+fn replace(view: &mut View, ch: char, times: usize) {
+    let at = view.text_location();
+    let end = Location {
+        grapheme_index: at.grapheme_index.saturating_add(times),
+        ..at
+    };
+    if end.grapheme_index > line_len(view, at.line_index) {
+        return;
+    }
+    view.delete_range(at, end);
+    if ch == '\n' {
+        view.insert_text("\n");
+        return;
+    }
+    view.insert_text(&ch.to_string().repeat(times));
+    view.set_text_location(Location {
+        grapheme_index: end.grapheme_index.saturating_sub(1),
+        ..at
+    });
+}
+
+fn join(view: &mut View, lines: usize) {
+    let line_index = view.text_location().line_index;
+    for _ in 1..lines {
+        let next_index = line_index.saturating_add(1);
+        let Some(next) = view.line(next_index) else {
+            break;
+        };
+        let indent_end = next.indent_end();
+        let next_empty = indent_end == next.len();
+        let closes = next.char_at(indent_end) == Some(')');
+        let join_at = line_end(view, line_index);
+        let ends_blank = join_at.grapheme_index == 0
+            || char_at(
+                view,
+                Location {
+                    grapheme_index: join_at.grapheme_index.saturating_sub(1),
+                    ..join_at
+                },
+            )
+            .is_some_and(char::is_whitespace);
+        view.delete_range(
+            join_at,
+            Location {
+                line_index: next_index,
+                grapheme_index: indent_end,
+            },
+        );
+        // 和 vim 一样用一个空格连接, 空行和右括号前不加
+        if !next_empty && !ends_blank && !closes {
+            view.insert_text(" ");
+        }
+        view.set_text_location(join_at);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::{KeyCode, KeyEvent};
+
+    // 模拟编辑器: 插入模式下 Pass 的按键由 View 处理
+    fn run(text: &str, keys: &str) -> (String, Location) {
+        let mut view = View::default();
+        view.load_scratch(text);
+        let mut vi = Vi::default();
+        let mut kill_ring = KillRing::default();
+        for ch in keys.chars() {
+            let code = if ch == '\x1b' {
+                KeyCode::Esc
+            } else {
+                KeyCode::Char(ch)
+            };
+            let key = Key::from(KeyEvent::from(code));
+            if matches!(vi.handle_key(key, &mut view, &mut kill_ring), Outcome::Pass)
+                && vi.mode() == Mode::Insert
+            {
+                view.handler_edit(Edit::Insert(ch));
+            }
+        }
+        let contents = view.contents();
+        let contents = contents.strip_suffix('\n').unwrap_or(&contents);
+        (contents.to_string(), view.text_location())
+    }
+
+    fn text(text: &str, keys: &str) -> String {
+        run(text, keys).0
+    }
+
+    #[test]
+    fn delete_word() {
+        assert_eq!(text("foo bar baz", "dw"), "bar baz");
+        assert_eq!(text("foo bar baz", "2dw"), "baz");
+        // 行尾最后一个单词不删换行
+        assert_eq!(text("foo bar\nbaz", "wdw"), "foo \nbaz");
+        assert_eq!(text("foo\n  bar", "dw"), "\n  bar");
+    }
+
+    #[test]
+    fn change_word() {
+        assert_eq!(text("foo bar", "cwxy\x1b"), "xy bar");
+        assert_eq!(text("foo bar", "2cwxy\x1b"), "xy");
+        // 行尾单词只改到词尾
+        assert_eq!(text("foo bar\nbaz", "wcwxy\x1b"), "foo xy\nbaz");
+        assert_eq!(text("a b", "cwx\x1b"), "x b");
+    }
+
+    #[test]
+    fn delete_lines() {
+        assert_eq!(text("a\nb\nc\nd", "dd"), "b\nc\nd");
+        assert_eq!(text("a\nb\nc\nd", "j3dd"), "a");
+        assert_eq!(text("a\nb", "5dd"), "");
+    }
+
+    #[test]
+    fn text_objects() {
+        assert_eq!(text("f(a, b) + c", "fbci(x\x1b"), "f(x) + c");
+        assert_eq!(text("say \"hi there\" now", "fhda\""), "say now");
+        assert_eq!(text("say \"hi\"", "fhdi\""), "say \"\"");
+        assert_eq!(text("say \"hi\"", "fhda\""), "say");
+    }
+
+    #[test]
+    fn insert_count() {
+        assert_eq!(text("ab", "3ix\x1b"), "xxxab");
+        assert_eq!(run("ab", "3ix\x1b").1.grapheme_index, 2);
+        assert_eq!(text("ab", "2Ayz\x1b"), "abyzyz");
+        assert_eq!(text("ab", "3ox\x1b"), "ab\nx\nx\nx");
+        assert_eq!(text("ab", "2Ox\x1b"), "x\nx\nab");
+    }
+
+    #[test]
+    fn repeat_change() {
+        assert_eq!(text("foo bar baz", "cwxy\x1bw."), "xy xy baz");
+        assert_eq!(text("a b c d", "dw."), "c d");
+        assert_eq!(text("ab", "ox\x1b."), "ab\nx\nx");
+    }
+
+    #[test]
+    fn put_lines() {
+        assert_eq!(text("a\nb\nc", "yyjp"), "a\nb\na\nc");
+        assert_eq!(text("a\nb\nc", "jyyP"), "a\nb\nb\nc");
+        assert_eq!(text("a\nb\nc", "ddp"), "b\na\nc");
+        assert_eq!(
+            run("a\n  b\nc", "jyyp").1,
+            Location {
+                line_index: 2,
+                grapheme_index: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn join_lines() {
+        assert_eq!(text("a\n  b\nc", "J"), "a b\nc");
+        assert_eq!(text("a\nb\nc\nd", "3J"), "a b c\nd");
+        assert_eq!(text("a \nb", "J"), "a b");
+    }
+
+    #[test]
+    fn find_till() {
+        assert_eq!(text("abcabc", "dtc"), "cabc");
+        assert_eq!(text("abcabc", "dfc"), "abc");
+        assert_eq!(text("abcabc", "2dtc"), "c");
+        // 紧挨着目标字符时 t 不移动
+        assert_eq!(text("axb", "dtx"), "axb");
+        assert_eq!(run("axb", "tx").1.grapheme_index, 0);
+        assert_eq!(text("axb", "$dTx"), "axb");
+        assert_eq!(text("axb", "dtz"), "axb");
+    }
+}
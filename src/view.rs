@@ -1,20 +1,33 @@
 use crate::{
     buffer::Buffer,
     command::{Edit, Move},
+    directory::DirEntry,
     documentstatus::DocumentStatus,
     editor::{NAME, VERSION},
+    filetype::FileType,
+    follow::{FollowReset, FollowUpdate, Follower},
+    gutter::GutterMode,
+    highlight::{Span, TokenType},
+    indent::{IndentSettings, IndentStyle},
     line::Line,
     position::Position,
     size::Size,
+    syntaxtree::NodeMotion,
     terminal::Terminal,
+    theme::Theme,
     uicomponent::UIComponent,
 };
-use std::{cmp::min, io::Error};
+use std::{
+    cmp::min,
+    io::Error,
+    path::{Path, PathBuf},
+};
 
-#[derive(Copy, Clone, Default)]
+// 字段顺序决定比较顺序: 先比较行, 再比较列
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Location {
-    pub grapheme_index: usize,
     pub line_index: usize,
+    pub grapheme_index: usize,
 }
 
 #[derive(Default)]
@@ -24,8 +37,24 @@ pub struct View {
     need_redraw: bool,
     text_location: Location,
     scroll_offset: Position,
+    follower: Option<Follower>,
+    // 跟随期间缓冲区只读, 停止后恢复原来的状态
+    readonly_before_follow: bool,
+    partial_shown: bool,
+    gutter_mode: GutterMode,
+    soft_wrap: bool,
+    scroll_subrow: usize,
+    sticky_column: Option<(Location, usize)>,
+    theme: Theme,
+    search_query: Option<String>,
+    scrolloff: usize,
+    selection: Option<(Location, Location)>,
+    mark: Option<Location>,
 }
 
+const WRAP_MARKER: &str = "↪";
+const WRAP_MARKER_WIDTH: usize = 1;
+
 impl View {
     pub fn get_status(&self) -> DocumentStatus {
         DocumentStatus {
@@ -33,30 +62,335 @@ impl View {
             total_line: self.buf.height(),
             filename: self.buf.file_info.to_string(),
             is_modified: self.buf.is_modify,
+            is_readonly: self.buf.is_readonly,
+            follow: self.follower.as_ref().map(|_| !self.is_at_bottom()),
+            indent: self.buf.indent.name(),
+            file_type: self.buf.file_info.file_type.name().to_string(),
+            pending_keys: String::new(),
+            mode: String::new(),
         }
     }
     pub fn load(&mut self, filename: &str) -> Result<(), Error> {
-        let buf = Buffer::read_file(filename)?;
+        let mut buf = Buffer::read_file(filename)?;
+        buf.set_tab_width(self.buf.tab_width());
         self.buf = buf;
+        self.text_location = Location::default();
+        self.scroll_offset = Position::default();
         self.mark_redraw(true);
         Ok(())
     }
 
+    pub fn load_text(&mut self, contents: &str) {
+        let tab_width = self.buf.tab_width();
+        self.buf = Buffer::from_text(contents);
+        self.buf.set_tab_width(tab_width);
+        self.text_location = Location::default();
+        self.scroll_offset = Position::default();
+        self.mark_redraw(true);
+    }
+
+    pub fn load_scratch(&mut self, contents: &str) {
+        self.load_text(contents);
+        self.buf.is_modify = !self.buf.is_empty();
+    }
+
+    pub fn set_readonly(&mut self, readonly: bool) {
+        self.buf.is_readonly = readonly;
+    }
+
+    pub fn set_file_type(&mut self, file_type: Option<FileType>) -> FileType {
+        match file_type {
+            Some(file_type) => self.buf.set_file_type(file_type),
+            None => self.buf.detect_file_type(),
+        }
+        self.mark_redraw(true);
+        self.buf.file_info.file_type
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.buf.is_modify
+    }
+
+    pub fn is_readonly(&self) -> bool {
+        self.buf.is_readonly
+    }
+
+    pub fn contents(&self) -> String {
+        self.buf.to_text()
+    }
+
+    pub fn is_blank(&self) -> bool {
+        !self.buf.is_file_loaded() && self.buf.is_empty()
+    }
+
     pub fn is_file_loaded(&self) -> bool {
         self.buf.is_file_loaded()
     }
 
-    pub fn render_line(at: usize, line: &str) -> Result<(), Error> {
-        Terminal::print_row(at, line)
+    pub fn file_path(&self) -> Option<&Path> {
+        self.buf.file_info.get_path()
+    }
+
+    pub const fn text_location(&self) -> Location {
+        self.text_location
+    }
+
+    pub const fn scroll_offset(&self) -> Position {
+        self.scroll_offset
+    }
+
+    pub fn restore_location(&mut self, location: Location, scroll_offset: Position) {
+        self.text_location = location;
+        self.snap_to_valid_line();
+        self.snap_to_valid_grapheme();
+        self.scroll_offset = scroll_offset;
+        self.scroll_text_location_into_view();
+        self.mark_redraw(true);
+    }
+
+    pub fn is_directory(&self) -> bool {
+        self.buf.is_directory()
+    }
+
+    pub fn selected_entry(&self) -> Option<&DirEntry> {
+        self.buf
+            .directory
+            .as_ref()
+            .and_then(|dir| dir.entry(self.text_location.line_index))
+    }
+
+    pub fn selected_entry_path(&self) -> Option<PathBuf> {
+        self.buf
+            .directory
+            .as_ref()
+            .and_then(|dir| dir.entry_path(self.text_location.line_index))
+    }
+
+    pub fn create_entry(&mut self, name: &str) -> Result<(), Error> {
+        if let Some(dir) = &self.buf.directory {
+            dir.create(name)?;
+        }
+        self.reload_directory()
+    }
+
+    pub fn rename_entry(&mut self, new_name: &str) -> Result<(), Error> {
+        if let Some(dir) = &self.buf.directory {
+            dir.rename(self.text_location.line_index, new_name)?;
+        }
+        self.reload_directory()
+    }
+
+    pub fn delete_entry(&mut self) -> Result<(), Error> {
+        if let Some(dir) = &self.buf.directory {
+            dir.delete(self.text_location.line_index)?;
+        }
+        self.reload_directory()
+    }
+
+    fn reload_directory(&mut self) -> Result<(), Error> {
+        if let Some(dir) = &self.buf.directory {
+            let tab_width = self.buf.tab_width();
+            self.buf = Buffer::read_dir(&dir.path)?;
+            self.buf.set_tab_width(tab_width);
+            self.text_location.line_index = min(
+                self.text_location.line_index,
+                self.buf.height().saturating_sub(1),
+            );
+            self.snap_to_valid_grapheme();
+            self.scroll_text_location_into_view();
+            self.mark_redraw(true);
+        }
+        Ok(())
+    }
+
+    pub fn render_line(&self, at: usize, line: &str) -> Result<(), Error> {
+        Terminal::print_styled_row(at, line, self.theme.text)
     }
 
-    pub fn handler_edit(&mut self, edit: Edit) {
+    fn line_spans(&self, line_index: usize) -> Vec<Span> {
+        let selected = self
+            .selection
+            .or_else(|| self.region())
+            .filter(|(start, end)| (start.line_index..=end.line_index).contains(&line_index))
+            .map(|(start, end)| {
+                let from = if line_index == start.line_index {
+                    start.grapheme_index
+                } else {
+                    0
+                };
+                let to = if line_index == end.line_index {
+                    end.grapheme_index
+                } else {
+                    self.buf.lines.get(line_index).map_or(0, Line::len)
+                };
+                Span {
+                    range: from..to,
+                    token: TokenType::Selection,
+                }
+            });
+        let mut spans: Vec<Span> = self
+            .search_query
+            .as_deref()
+            .zip(self.buf.lines.get(line_index))
+            .map(|(query, line)| line.find_all(query))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|range| Span {
+                range,
+                token: TokenType::SearchMatch,
+            })
+            .collect();
+        // 选区和搜索结果放在前面, 覆盖语法高亮
+        spans.splice(0..0, selected);
+        spans.extend_from_slice(self.buf.spans(line_index));
+        spans
+    }
+
+    pub fn handler_edit(&mut self, edit: Edit) -> Option<String> {
+        if self.buf.is_readonly {
+            return None;
+        }
+        self.sticky_column = None;
+        self.mark = None;
         match edit {
             Edit::Delete => self.delete_backward(),
+            Edit::Insert('\t') => self.insert_indent(),
+            Edit::Insert(c) if self.buf.indent_rules().closers.contains(&c) => {
+                self.dedent_for_closer();
+                self.insert_char(c);
+            }
             Edit::Insert(c) => self.insert_char(c),
             Edit::InsertNewline => self.insert_new_line(),
             Edit::DeleteBackward => self.backspace(),
+            Edit::DeleteWordBackward
+            | Edit::DeleteWordForward
+            | Edit::KillToEndOfLine
+            | Edit::KillToStartOfLine
+            | Edit::DeleteLine => return self.kill(edit),
+        }
+        None
+    }
+
+    fn kill(&mut self, edit: Edit) -> Option<String> {
+        let at = self.text_location;
+        let (location, killed) = match edit {
+            Edit::DeleteWordBackward => self.buf.delete_word_backward(at),
+            Edit::DeleteWordForward => self.buf.delete_word_forward(at),
+            Edit::KillToEndOfLine => self.buf.kill_to_end_of_line(at),
+            Edit::KillToStartOfLine => self.buf.kill_to_start_of_line(at),
+            Edit::DeleteLine => self.buf.delete_line(at),
+            _ => return None,
+        };
+        self.text_location = location;
+        self.scroll_text_location_into_view();
+        self.mark_redraw(true);
+        Some(killed).filter(|killed| !killed.is_empty())
+    }
+
+    pub fn delete_range(&mut self, start: Location, end: Location) -> String {
+        if self.buf.is_readonly {
+            return String::new();
         }
+        self.sticky_column = None;
+        self.mark = None;
+        let killed = self.buf.delete_range(start, end);
+        self.set_text_location(start);
+        self.mark_redraw(true);
+        killed
+    }
+
+    pub fn text_range(&self, start: Location, end: Location) -> String {
+        self.buf.text_range(start, end)
+    }
+
+    pub fn line(&self, line_index: usize) -> Option<&Line> {
+        self.buf.lines.get(line_index)
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.buf.height()
+    }
+
+    // 按整行删除, 返回的文本以换行结尾
+    pub fn delete_lines(&mut self, first: usize, last: usize) -> String {
+        let line_start = |line_index| Location {
+            line_index,
+            grapheme_index: 0,
+        };
+        let line_end = |line_index| Location {
+            line_index,
+            grapheme_index: self.buf.lines.get(line_index).map_or(0, Line::len),
+        };
+        let killed = format!("{}\n", self.text_range(line_start(first), line_end(last)));
+        let (start, end) = if last.saturating_add(1) < self.line_count() {
+            (line_start(first), line_start(last.saturating_add(1)))
+        } else if first > 0 {
+            // 删除到文件末尾时连同前一行的换行一起删除
+            (line_end(first.saturating_sub(1)), line_end(last))
+        } else {
+            (line_start(first), line_end(last))
+        };
+        self.delete_range(start, end);
+        self.set_text_location(line_start(first.min(self.line_count().saturating_sub(1))));
+        killed
+    }
+
+    pub fn replace_line(&mut self, line_index: usize, text: &str) {
+        if self.buf.is_readonly {
+            return;
+        }
+        let start = Location {
+            line_index,
+            grapheme_index: 0,
+        };
+        let end = Location {
+            line_index,
+            grapheme_index: self.buf.lines.get(line_index).map_or(0, Line::len),
+        };
+        self.mark = None;
+        self.buf.delete_range(start, end);
+        self.buf.insert_text(text, start);
+        self.mark_redraw(true);
+    }
+
+    pub fn set_selection(&mut self, selection: Option<(Location, Location)>) {
+        if self.selection != selection {
+            self.selection = selection;
+            self.mark_redraw(true);
+        }
+    }
+
+    // 在同一位置再次设置则取消标记
+    pub fn set_mark(&mut self) -> bool {
+        self.mark = if self.mark == Some(self.text_location) {
+            None
+        } else {
+            Some(self.text_location)
+        };
+        self.mark_redraw(true);
+        self.mark.is_some()
+    }
+
+    pub fn clear_mark(&mut self) {
+        if self.mark.take().is_some() {
+            self.mark_redraw(true);
+        }
+    }
+
+    pub fn region(&self) -> Option<(Location, Location)> {
+        self.mark
+            .map(|mark| (mark.min(self.text_location), mark.max(self.text_location)))
+    }
+
+    pub fn insert_text(&mut self, text: &str) {
+        if self.buf.is_readonly {
+            return;
+        }
+        self.sticky_column = None;
+        self.mark = None;
+        self.text_location = self.buf.insert_text(text, self.text_location);
+        self.scroll_text_location_into_view();
+        self.mark_redraw(true);
     }
 
     pub fn insert_char(&mut self, s: char) {
@@ -71,15 +405,78 @@ impl View {
         self.mark_redraw(true);
     }
 
+    fn insert_indent(&mut self) {
+        let IndentSettings { style, width } = self.buf.indent;
+        match style {
+            IndentStyle::Tabs => self.insert_char('\t'),
+            IndentStyle::Spaces => {
+                let column = self.text_location_to_postion().col;
+                let width = width.max(1);
+                for _ in 0..width.saturating_sub(column % width) {
+                    self.insert_char(' ');
+                }
+            }
+        }
+    }
+
+    fn dedent_for_closer(&mut self) {
+        let Location {
+            line_index,
+            grapheme_index,
+        } = self.text_location;
+        let at_indent_end = self
+            .buf
+            .lines
+            .get(line_index)
+            .is_some_and(|line| grapheme_index > 0 && line.indent_end() == grapheme_index);
+        if at_indent_end {
+            for _ in 0..self.dedent_width() {
+                self.move_left();
+                self.delete_backward();
+            }
+        }
+    }
+
+    fn dedent_width(&self) -> usize {
+        let Location {
+            line_index,
+            grapheme_index,
+        } = self.text_location;
+        let IndentSettings { style, width } = self.buf.indent;
+        let Some(line) = self.buf.lines.get(line_index) else {
+            return 1;
+        };
+        if style == IndentStyle::Tabs || grapheme_index == 0 || grapheme_index > line.indent_end() {
+            return 1;
+        }
+        // 在行首空白中退格时删除一整级缩进
+        let column = line.width_until(grapheme_index);
+        let step = column.saturating_sub(1) % width.max(1) + 1;
+        let spaces = (0..grapheme_index)
+            .rev()
+            .take_while(|index| line.char_at(*index) == Some(' '))
+            .count();
+        step.min(spaces).max(1)
+    }
+
     pub fn insert_new_line(&mut self) {
-        self.buf.insert_new_line(self.text_location);
-        self.move_text_location(Move::Right);
+        let grapheme_index = self.buf.insert_new_line(self.text_location);
+        self.text_location = Location {
+            line_index: self.text_location.line_index.saturating_add(1),
+            grapheme_index,
+        };
+        self.scroll_text_location_into_view();
         self.mark_redraw(true);
     }
 
     pub fn backspace(&mut self) {
-        self.move_left();
-        self.delete_backward();
+        if self.text_location.line_index == 0 && self.text_location.grapheme_index == 0 {
+            return;
+        }
+        for _ in 0..self.dedent_width() {
+            self.move_left();
+            self.delete_backward();
+        }
     }
 
     pub fn delete_backward(&mut self) {
@@ -90,13 +487,23 @@ impl View {
         self.mark_redraw(true);
     }
 
+    fn scroll_margin(&self) -> usize {
+        self.scrolloff.min(self.size.height.saturating_sub(1) / 2)
+    }
+
     fn scroll_vertically(&mut self, to: usize) {
         let height = self.size.height;
-        let changed = if to < self.scroll_offset.row {
-            self.scroll_offset.row = to;
+        let margin = self.scroll_margin();
+        let top = to.saturating_sub(margin);
+        // 底部的留白不超过最后一行
+        let bottom = to
+            .saturating_add(margin)
+            .min(self.buf.height().saturating_sub(1).max(to));
+        let changed = if top < self.scroll_offset.row {
+            self.scroll_offset.row = top;
             true
-        } else if to >= self.scroll_offset.row.saturating_add(height) {
-            self.scroll_offset.row = to.saturating_sub(height).saturating_add(1);
+        } else if bottom >= self.scroll_offset.row.saturating_add(height) {
+            self.scroll_offset.row = bottom.saturating_sub(height).saturating_add(1);
             true
         } else {
             false
@@ -106,8 +513,214 @@ impl View {
         }
     }
 
+    pub fn set_gutter_mode(&mut self, mode: GutterMode) {
+        self.gutter_mode = mode;
+        self.scroll_text_location_into_view();
+        self.mark_redraw(true);
+    }
+
+    pub fn set_tab_width(&mut self, tab_width: usize) {
+        self.buf.set_tab_width(tab_width);
+        self.scroll_text_location_into_view();
+        self.mark_redraw(true);
+    }
+
+    pub fn set_scrolloff(&mut self, scrolloff: usize) {
+        self.scrolloff = scrolloff;
+        self.scroll_text_location_into_view();
+    }
+
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+        self.mark_redraw(true);
+    }
+
+    pub fn set_soft_wrap(&mut self, soft_wrap: bool) {
+        self.soft_wrap = soft_wrap;
+        self.scroll_offset.col = 0;
+        self.scroll_subrow = 0;
+        self.scroll_text_location_into_view();
+        self.mark_redraw(true);
+    }
+
+    fn wrap_starts(&self, line_index: usize) -> Vec<usize> {
+        let width = self.text_width();
+        self.buf.lines.get(line_index).map_or_else(
+            || vec![0],
+            |line| line.wrap(width, width.saturating_sub(WRAP_MARKER_WIDTH)),
+        )
+    }
+
+    fn visual_row(&self, location: Location) -> (usize, usize) {
+        let starts = self.wrap_starts(location.line_index);
+        let subrow = starts
+            .iter()
+            .rposition(|start| *start <= location.grapheme_index)
+            .unwrap_or(0);
+        (location.line_index, subrow)
+    }
+
+    fn previous_visual_row(&self, (line_index, subrow): (usize, usize)) -> Option<(usize, usize)> {
+        if subrow > 0 {
+            Some((line_index, subrow.saturating_sub(1)))
+        } else if line_index > 0 {
+            let previous = line_index.saturating_sub(1);
+            Some((previous, self.wrap_starts(previous).len().saturating_sub(1)))
+        } else {
+            None
+        }
+    }
+
+    fn next_visual_row(&self, (line_index, subrow): (usize, usize)) -> Option<(usize, usize)> {
+        if subrow.saturating_add(1) < self.wrap_starts(line_index).len() {
+            Some((line_index, subrow.saturating_add(1)))
+        } else if line_index < self.buf.height() {
+            Some((line_index.saturating_add(1), 0))
+        } else {
+            None
+        }
+    }
+
+    fn scroll_wrapped_into_view(&mut self) {
+        let height = self.size.height.max(1);
+        let cursor = self.visual_row(self.text_location);
+        let top_rows = self.wrap_starts(self.scroll_offset.row).len();
+        let top = (
+            self.scroll_offset.row,
+            self.scroll_subrow.min(top_rows.saturating_sub(1)),
+        );
+        let margin = self.scroll_margin();
+        let (mut upper, mut lower) = (cursor, cursor);
+        for _ in 0..margin {
+            upper = self.previous_visual_row(upper).unwrap_or(upper);
+            lower = self
+                .next_visual_row(lower)
+                .filter(|(line_index, _)| *line_index < self.buf.height())
+                .unwrap_or(lower);
+        }
+        let new_top = if upper < top {
+            upper
+        } else {
+            let mut row = lower;
+            for _ in 1..height {
+                if row == top {
+                    break;
+                }
+                match self.previous_visual_row(row) {
+                    Some(previous) => row = previous,
+                    None => break,
+                }
+            }
+            if row < top {
+                top
+            } else {
+                row
+            }
+        };
+        if new_top != (self.scroll_offset.row, self.scroll_subrow) {
+            (self.scroll_offset.row, self.scroll_subrow) = new_top;
+            self.mark_redraw(true);
+        }
+    }
+
+    fn wrapped_caret_position(&self) -> Position {
+        let (line_index, subrow) = self.visual_row(self.text_location);
+        let top = (self.scroll_offset.row, self.scroll_subrow);
+        let mut current = (line_index, subrow);
+        let mut row: usize = 0;
+        while current > top {
+            match self.previous_visual_row(current) {
+                Some(previous) => current = previous,
+                None => break,
+            }
+            row = row.saturating_add(1);
+        }
+        let start = self
+            .wrap_starts(line_index)
+            .get(subrow)
+            .copied()
+            .unwrap_or(0);
+        let col = self.buf.lines.get(line_index).map_or(0, |line| {
+            line.width_until(self.text_location.grapheme_index)
+                .saturating_sub(line.width_until(start))
+        });
+        let marker_width = if subrow > 0 { WRAP_MARKER_WIDTH } else { 0 };
+        Position {
+            col: col
+                .saturating_add(marker_width)
+                .saturating_add(self.gutter_width()),
+            row,
+        }
+    }
+
+    fn move_visually(&mut self, up: bool, step: usize, col: usize) {
+        for _ in 0..step {
+            let current = self.visual_row(self.text_location);
+            let target = if up {
+                self.previous_visual_row(current)
+            } else {
+                self.next_visual_row(current)
+            };
+            let Some((target_line, target_subrow)) = target else {
+                break;
+            };
+            let target_starts = self.wrap_starts(target_line);
+            let target_start = target_starts.get(target_subrow).copied().unwrap_or(0);
+            let grapheme_index = self.buf.lines.get(target_line).map_or(0, |line| {
+                let end = target_starts
+                    .get(target_subrow.saturating_add(1))
+                    .map_or(line.len(), |next| next.saturating_sub(1));
+                let base = line.width_until(target_start);
+                line.grapheme_at_column(base.saturating_add(col))
+                    .clamp(target_start, end)
+            });
+            self.text_location = Location {
+                line_index: target_line,
+                grapheme_index,
+            };
+        }
+    }
+
+    fn visual_column(&self) -> usize {
+        let Location {
+            line_index,
+            grapheme_index,
+        } = self.text_location;
+        let start = if self.soft_wrap {
+            let (_, subrow) = self.visual_row(self.text_location);
+            self.wrap_starts(line_index)
+                .get(subrow)
+                .copied()
+                .unwrap_or(0)
+        } else {
+            0
+        };
+        self.buf.lines.get(line_index).map_or(0, |line| {
+            line.width_until(grapheme_index)
+                .saturating_sub(line.width_until(start))
+        })
+    }
+
+    fn snap_to_column(&mut self, col: usize) {
+        self.text_location.grapheme_index = self
+            .buf
+            .lines
+            .get(self.text_location.line_index)
+            .map_or(0, |line| line.grapheme_at_column(col));
+    }
+
+    fn gutter_width(&self) -> usize {
+        self.gutter_mode
+            .width(self.buf.height())
+            .min(self.size.width.saturating_sub(1))
+    }
+
+    fn text_width(&self) -> usize {
+        self.size.width.saturating_sub(self.gutter_width())
+    }
+
     fn scroll_heriztion(&mut self, to: usize) {
-        let width = self.size.width;
+        let width = self.text_width();
         let changed = if to < self.scroll_offset.col {
             self.scroll_offset.col = to;
             true
@@ -138,22 +751,107 @@ impl View {
         }
     }
 
+    pub fn caret_position(&self) -> Position {
+        if self.soft_wrap {
+            return self.wrapped_caret_position();
+        }
+        let Position { col, row } = self
+            .text_location_to_postion()
+            .saturating_sub(self.scroll_offset);
+        Position {
+            col: col.saturating_add(self.gutter_width()),
+            row,
+        }
+    }
+
     pub fn move_text_location(&mut self, mv: Move) {
         let Size { height, .. } = self.size;
-        let _ = match mv {
+        let page = height.saturating_sub(1);
+        // 上下移动时保持期望的列, 直到水平移动为止
+        let col = self
+            .sticky_column
+            .filter(|(at, _)| *at == self.text_location)
+            .map_or_else(|| self.visual_column(), |(_, col)| col);
+        match mv {
+            Move::Up if self.soft_wrap => self.move_visually(true, 1, col),
+            Move::Down if self.soft_wrap => self.move_visually(false, 1, col),
+            Move::PageDown if self.soft_wrap => self.move_visually(false, page, col),
+            Move::PageUp if self.soft_wrap => self.move_visually(true, page, col),
             Move::Up => self.move_up(1),
             Move::Down => self.move_down(1),
             Move::Left => self.move_left(),
             Move::Right => self.move_right(),
-            Move::PageDown => self.move_down(height.saturating_sub(1)),
-            Move::PageUp => self.move_up(height.saturating_sub(1)),
-            Move::Home => self.move_start_of_line(),
+            Move::PageDown => self.move_down(page),
+            Move::PageUp => self.move_up(page),
+            Move::Home => self.move_smart_home(),
             Move::End => self.move_end_of_line(),
+            Move::WordLeft => self.move_word_left(),
+            Move::WordRight => self.move_word_right(),
+            Move::ParagraphUp => self.move_paragraph(true),
+            Move::ParagraphDown => self.move_paragraph(false),
+            Move::StartOfDocument => self.text_location = Location::default(),
+            Move::ParentNode => self.move_to_node(NodeMotion::Parent),
+            Move::NextSibling => self.move_to_node(NodeMotion::NextSibling),
+            Move::PreviousSibling => self.move_to_node(NodeMotion::PreviousSibling),
+            Move::EndOfDocument => {
+                self.text_location.line_index = self.buf.height().saturating_sub(1);
+                self.move_end_of_line();
+            }
+        };
+        self.sticky_column = match mv {
+            Move::Up | Move::Down | Move::PageUp | Move::PageDown => {
+                if !self.soft_wrap {
+                    self.snap_to_column(col);
+                }
+                Some((self.text_location, col))
+            }
+            _ => None,
         };
+        // 相对行号和标记区域都随光标变化
+        if self.gutter_mode.is_relative() || self.mark.is_some() {
+            self.mark_redraw(true);
+        }
+        self.scroll_text_location_into_view();
+    }
+
+    pub fn set_text_location(&mut self, location: Location) {
+        self.text_location = location;
+        self.snap_to_valid_line();
+        self.snap_to_valid_grapheme();
+        self.sticky_column = None;
+        if self.gutter_mode.is_relative() || self.mark.is_some() {
+            self.mark_redraw(true);
+        }
+        self.scroll_text_location_into_view();
+    }
+
+    // 普通模式下光标落在最后一个字符上, 而不是行尾之后
+    pub fn snap_to_last_grapheme(&mut self) {
+        let last = self
+            .buf
+            .lines
+            .get(self.text_location.line_index)
+            .map_or(0, |line| line.len().saturating_sub(1));
+        if self.text_location.grapheme_index <= last {
+            return;
+        }
+        let previous = self.text_location;
+        self.text_location.grapheme_index = last;
+        if let Some((at, _)) = self
+            .sticky_column
+            .as_mut()
+            .filter(|(at, _)| *at == previous)
+        {
+            *at = self.text_location;
+        }
         self.scroll_text_location_into_view();
     }
 
     pub fn scroll_text_location_into_view(&mut self) {
+        if self.soft_wrap {
+            self.scroll_wrapped_into_view();
+            return;
+        }
         let pos = self.text_location_to_postion();
         self.scroll_heriztion(pos.col);
         self.scroll_vertically(pos.row);
@@ -197,6 +895,90 @@ impl View {
         self.text_location.grapheme_index = 0;
     }
 
+    fn move_smart_home(&mut self) {
+        let indent_end = self
+            .buf
+            .lines
+            .get(self.text_location.line_index)
+            .map_or(0, Line::indent_end);
+        // 先跳到第一个非空白字符, 再按一次回到行首
+        self.text_location.grapheme_index = if self.text_location.grapheme_index == indent_end {
+            0
+        } else {
+            indent_end
+        };
+    }
+
+    fn move_word_left(&mut self) {
+        let Location {
+            line_index,
+            grapheme_index,
+        } = self.text_location;
+        match self
+            .buf
+            .lines
+            .get(line_index)
+            .and_then(|line| line.previous_word_start(grapheme_index))
+        {
+            Some(start) => self.text_location.grapheme_index = start,
+            None if grapheme_index > 0 => self.move_start_of_line(),
+            None => self.move_left(),
+        }
+    }
+
+    fn move_word_right(&mut self) {
+        let Location {
+            line_index,
+            grapheme_index,
+        } = self.text_location;
+        let line = self.buf.lines.get(line_index);
+        match line.and_then(|line| line.next_word_end(grapheme_index)) {
+            Some(end) => self.text_location.grapheme_index = end,
+            None if grapheme_index < line.map_or(0, Line::len) => self.move_end_of_line(),
+            None => self.move_right(),
+        }
+    }
+
+    fn move_to_node(&mut self, motion: NodeMotion) {
+        if let Some(location) = self.buf.node_target(self.text_location, motion) {
+            self.text_location = location;
+        }
+    }
+
+    fn move_paragraph(&mut self, up: bool) {
+        let is_blank = |index: usize| self.buf.lines.get(index).is_none_or(Line::is_blank);
+        let height = self.buf.height();
+        let mut index = self.text_location.line_index;
+        // 先跳过空行, 再找到段落后面的第一个空行
+        let mut in_paragraph = false;
+        loop {
+            if up {
+                if index == 0 {
+                    break;
+                }
+                index = index.saturating_sub(1);
+            } else {
+                if index.saturating_add(1) >= height {
+                    index = height.saturating_sub(1);
+                    break;
+                }
+                index = index.saturating_add(1);
+            }
+            if !is_blank(index) {
+                in_paragraph = true;
+            } else if in_paragraph {
+                break;
+            }
+        }
+        self.text_location = Location {
+            line_index: index,
+            grapheme_index: 0,
+        };
+        if !up && !is_blank(index) {
+            self.move_end_of_line();
+        }
+    }
+
     fn move_end_of_line(&mut self) {
         self.text_location.grapheme_index = self
             .buf
@@ -217,6 +999,117 @@ impl View {
         self.text_location.line_index = min(self.text_location.line_index, self.buf.height());
     }
 
+    pub fn start_follow(&mut self) -> Result<(), Error> {
+        if self.buf.is_modify {
+            return Err(Error::other("Save the changes before following the file"));
+        }
+        let Some(path) = self.file_path().map(Path::to_path_buf) else {
+            return Err(Error::other("Only files on disk can be followed"));
+        };
+        let (follower, update) = Follower::open(&path)?;
+        self.buf.clear_lines();
+        self.partial_shown = false;
+        self.follower = Some(follower);
+        self.readonly_before_follow = self.buf.is_readonly;
+        self.buf.is_readonly = true;
+        self.apply_follow_update(update);
+        self.jump_to_line(usize::MAX);
+        Ok(())
+    }
+
+    pub fn stop_follow(&mut self) {
+        if self.follower.take().is_some() {
+            self.buf.is_readonly = self.readonly_before_follow;
+        }
+        self.partial_shown = false;
+    }
+
+    pub fn is_following(&self) -> bool {
+        self.follower.is_some()
+    }
+
+    pub fn is_at_bottom(&self) -> bool {
+        self.text_location.line_index.saturating_add(1) >= self.buf.height()
+    }
+
+    pub fn poll_follow(&mut self) -> Option<FollowReset> {
+        let update = self.follower.as_mut()?.poll().ok()??;
+        let reset = update.reset;
+        self.apply_follow_update(update);
+        reset
+    }
+
+    fn apply_follow_update(&mut self, update: FollowUpdate) {
+        let at_bottom = self.is_at_bottom();
+        if update.reset.is_some() {
+            // 截断或轮转后会从头重新读取整个文件
+            self.buf.clear_lines();
+            self.partial_shown = false;
+        } else if self.partial_shown {
+            self.buf.pop_line();
+            self.partial_shown = false;
+        }
+        for line in &update.lines {
+            self.buf.push_line(line);
+        }
+        if let Some(partial) = &update.partial {
+            self.buf.push_line(partial);
+            self.partial_shown = true;
+        }
+        if at_bottom {
+            self.jump_to_line(usize::MAX);
+        } else {
+            self.snap_to_valid_line();
+            self.snap_to_valid_grapheme();
+        }
+        self.mark_redraw(true);
+    }
+
+    pub fn jump_to_line(&mut self, line_index: usize) {
+        self.text_location = Location {
+            line_index: min(line_index, self.buf.height().saturating_sub(1)),
+            grapheme_index: 0,
+        };
+        self.scroll_text_location_into_view();
+    }
+
+    pub fn search_next(&mut self, query: &str) -> bool {
+        if query.is_empty() || self.buf.is_empty() {
+            return false;
+        }
+        if self.search_query.as_deref() != Some(query) {
+            self.search_query = Some(query.to_string());
+            self.mark_redraw(true);
+        }
+        let Location {
+            line_index,
+            grapheme_index,
+        } = self.text_location;
+        let height = self.buf.height();
+        for offset in 0..=height {
+            let current = line_index.saturating_add(offset) % height;
+            let from = if offset == 0 {
+                grapheme_index.saturating_add(1)
+            } else {
+                0
+            };
+            if let Some(found) = self
+                .buf
+                .lines
+                .get(current)
+                .and_then(|line| line.search_forward(query, from))
+            {
+                self.text_location = Location {
+                    line_index: current,
+                    grapheme_index: found,
+                };
+                self.scroll_text_location_into_view();
+                return true;
+            }
+        }
+        false
+    }
+
     pub fn save(&mut self) -> Result<(), Error> {
         self.buf.save()
     }
@@ -260,18 +1153,63 @@ impl UIComponent for View {
         #[allow(clippy::as_conversions)]
         let top_third = height / 3;
         let scroll_top = self.scroll_offset.row;
+        let gutter_width = self.gutter_width();
+        let text_width = self.text_width();
+        let mut visual_row = Some((scroll_top, self.scroll_subrow));
+        self.buf
+            .highlight(scroll_top..scroll_top.saturating_add(height));
         for current_row in origin_y..end_y {
-            let line_idx = current_row
-                .saturating_sub(origin_y)
-                .saturating_add(scroll_top);
+            let line_idx = if self.soft_wrap {
+                visual_row.map_or(usize::MAX, |(line_index, _)| line_index)
+            } else {
+                current_row
+                    .saturating_sub(origin_y)
+                    .saturating_add(scroll_top)
+            };
             if let Some(line) = self.buf.lines.get(line_idx) {
-                let left = self.scroll_offset.col;
-                let right = self.scroll_offset.col.saturating_add(width);
-                Self::render_line(current_row, &line.get(left..right))?;
+                let current_line = self.text_location.line_index;
+                if let Some((_, subrow)) = visual_row.filter(|_| self.soft_wrap) {
+                    let starts = self.wrap_starts(line_idx);
+                    let start = starts.get(subrow).copied().unwrap_or(0);
+                    let end = starts
+                        .get(subrow.saturating_add(1))
+                        .copied()
+                        .unwrap_or(line.len());
+                    let prefix = if subrow == 0 {
+                        self.gutter_mode.label(line_idx, current_line, gutter_width)
+                    } else {
+                        format!("{:gutter_width$}{WRAP_MARKER}", "")
+                    };
+                    self.render_line(
+                        current_row,
+                        &format!(
+                            "{}{}",
+                            self.theme.gutter().apply(prefix),
+                            line.get_graphemes(start..end, &self.line_spans(line_idx), &self.theme)
+                        ),
+                    )?;
+                    visual_row = self.next_visual_row((line_idx, subrow));
+                } else {
+                    let left = self.scroll_offset.col;
+                    let right = self.scroll_offset.col.saturating_add(text_width);
+                    let gutter = self.gutter_mode.label(line_idx, current_line, gutter_width);
+                    self.render_line(
+                        current_row,
+                        &format!(
+                            "{}{}",
+                            self.theme.gutter().apply(gutter),
+                            line.get_highlighted(
+                                left..right,
+                                &self.line_spans(line_idx),
+                                &self.theme
+                            )
+                        ),
+                    )?;
+                }
             } else if current_row == top_third && self.buf.is_empty() {
-                Self::render_line(current_row, &Self::buid_welcome_message(width))?;
+                self.render_line(current_row, &Self::buid_welcome_message(width))?;
             } else {
-                Self::render_line(current_row, "~")?;
+                self.render_line(current_row, "~")?;
             }
         }
         Ok(())